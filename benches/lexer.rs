@@ -0,0 +1,107 @@
+/*
+ * lexes (and parses) generated sources of about 1MB and reports the time taken
+ * and how many heap allocations were made, run with `cargo bench`
+ */
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fmt::Write,
+    mem,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use orange::prelude::*;
+
+// counts every allocation so the lexer's share can be measured
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const SOURCE_SIZE: usize = 1024 * 1024;
+const RUNS: u32 = 10;
+
+// a mix of every kind of token, each line using fresh names
+fn mixed_source() -> String {
+    let mut source = String::with_capacity(SOURCE_SIZE + 128);
+    let mut line = 0;
+
+    while source.len() < SOURCE_SIZE {
+        writeln!(
+            source,
+            "let value_{line} = (count_{line} + 1_000) * 0x2A - total / 3.5e2; # line {line}\n\
+             const label_{line} = \"item {{value_{line}}} of {{total}}\\n\";",
+        )
+        .unwrap();
+        line += 1;
+    }
+
+    source
+}
+
+// the same handful of names over and over, as in a long function body
+fn repeated_source() -> String {
+    let mut source = String::with_capacity(SOURCE_SIZE + 128);
+
+    while source.len() < SOURCE_SIZE {
+        source.push_str("counter = counter + step * (counter - total) / step;\n");
+    }
+
+    source
+}
+
+// runs lex (and parse, if asked) RUNS times and prints the average time and allocations
+fn measure(name: &str, source: &str, parse: bool) {
+    let mut tokens = 0;
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let started = Instant::now();
+
+    for _ in 0..RUNS {
+        let mut lexer = Lexer::from_source(source);
+        lexer.tokenize().unwrap();
+        tokens = lexer.tokens.len();
+
+        if parse {
+            let tokens = mem::take(&mut lexer.tokens);
+            let interner = lexer.take_interner();
+            let (_, errors) = Parser::new(tokens, lexer.source(), interner).parse();
+            assert!(errors.is_empty());
+        }
+    }
+
+    let elapsed = started.elapsed() / RUNS;
+    let allocations = (ALLOCATIONS.load(Ordering::Relaxed) - before) / RUNS as usize;
+
+    println!("{}", name);
+    println!("  source:      {} bytes", source.len());
+    println!("  tokens:      {}", tokens);
+    println!("  time:        {:?} per run", elapsed);
+    println!(
+        "  allocations: {} per run, {:.2} per token",
+        allocations,
+        allocations as f64 / tokens as f64
+    );
+}
+
+fn main() {
+    measure("lexing, mixed tokens", &mixed_source(), false);
+    measure(
+        "lexing and parsing, repeated names",
+        &repeated_source(),
+        true,
+    );
+}
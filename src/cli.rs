@@ -0,0 +1,221 @@
+use std::fmt::{self, Display};
+
+pub const USAGE: &str = "\
+usage: orange [options] <script>    (use - to read the script from stdin)
+       orange [options] -e <code>...
+
+options:
+    -h, --help              print this message
+    -V, --version           print the version
+    -e, --eval <code>       run <code> instead of a script, repeat to add lines
+    --tokens[=text|json]    print the token stream and exit
+    --ast[=debug|json|sexpr]
+                            print the parsed program and exit
+    --no-semicolons         let statements end at a line break
+    --time[=text|json]      report how long each phase took on stderr";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DumpFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AstFormat {
+    Debug,
+    Json,
+    Sexpr,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    pub script: Option<String>,
+    pub help: bool,
+    pub version: bool,
+    pub tokens: Option<DumpFormat>,
+    pub ast: Option<AstFormat>,
+    pub no_semicolons: bool,
+    pub eval: Vec<String>,
+    pub time: Option<DumpFormat>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CliError {
+    UnknownFlag(String),
+    MissingValue(String),
+    UnexpectedArgument(String),
+    InvalidValue { flag: String, value: String },
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownFlag(flag) => write!(f, "unknown flag '{}'", flag),
+            Self::MissingValue(flag) => write!(f, "'{}' expects a value", flag),
+            Self::UnexpectedArgument(arg) => write!(f, "unexpected argument '{}'", arg),
+            Self::InvalidValue { flag, value } => {
+                write!(f, "invalid value '{}' for '{}'", value, flag)
+            }
+        }
+    }
+}
+
+/*
+ * parses the arguments that follow the program name
+ * the first positional argument is the script, anything starting with '-' is a flag
+ * code given through -e replaces the script, so a positional argument is then rejected
+ */
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, CliError> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => options.help = true,
+            "-V" | "--version" => options.version = true,
+            "-e" | "--eval" => match args.next() {
+                Some(code) => options.eval.push(code),
+                None => return Err(CliError::MissingValue(arg)),
+            },
+            "--tokens" => options.tokens = Some(DumpFormat::Text),
+            flag if flag.starts_with("--tokens=") => {
+                options.tokens = Some(dump_format("--tokens", &flag["--tokens=".len()..])?)
+            }
+            "--ast" => options.ast = Some(AstFormat::Debug),
+            flag if flag.starts_with("--ast=") => {
+                options.ast = Some(ast_format(&flag["--ast=".len()..])?)
+            }
+            "--no-semicolons" => options.no_semicolons = true,
+            "--time" => options.time = Some(DumpFormat::Text),
+            flag if flag.starts_with("--time=") => {
+                options.time = Some(dump_format("--time", &flag["--time=".len()..])?)
+            }
+            flag if flag.starts_with('-') && flag != "-" => return Err(CliError::UnknownFlag(arg)),
+            _ if options.script.is_none() => options.script = Some(arg),
+            _ => return Err(CliError::UnexpectedArgument(arg)),
+        }
+    }
+
+    if let (false, Some(script)) = (options.eval.is_empty(), &options.script) {
+        return Err(CliError::UnexpectedArgument(script.clone()));
+    }
+
+    Ok(options)
+}
+
+fn dump_format(flag: &str, value: &str) -> Result<DumpFormat, CliError> {
+    match value {
+        "text" => Ok(DumpFormat::Text),
+        "json" => Ok(DumpFormat::Json),
+        _ => Err(CliError::InvalidValue {
+            flag: flag.to_string(),
+            value: value.to_string(),
+        }),
+    }
+}
+
+fn ast_format(value: &str) -> Result<AstFormat, CliError> {
+    match value {
+        "debug" => Ok(AstFormat::Debug),
+        "json" => Ok(AstFormat::Json),
+        "sexpr" => Ok(AstFormat::Sexpr),
+        _ => Err(CliError::InvalidValue {
+            flag: String::from("--ast"),
+            value: value.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, CliError> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+    }
+
+    #[test]
+    fn script_path() {
+        let options = parse(&["main.ong"]).unwrap();
+        assert_eq!(options.script.as_deref(), Some("main.ong"));
+
+        let options = parse(&["-"]).unwrap();
+        assert_eq!(options.script.as_deref(), Some("-"));
+    }
+
+    #[test]
+    fn unknown_flag() {
+        assert_eq!(
+            parse(&["--nope", "main.ong"]),
+            Err(CliError::UnknownFlag(String::from("--nope")))
+        );
+        assert_eq!(
+            parse(&["a.ong", "b.ong"]),
+            Err(CliError::UnexpectedArgument(String::from("b.ong")))
+        );
+    }
+
+    #[test]
+    fn dump_flags() {
+        let options = parse(&["--tokens", "--ast", "--time=json", "main.ong"]).unwrap();
+        assert_eq!(options.tokens, Some(DumpFormat::Text));
+        assert_eq!(options.ast, Some(AstFormat::Debug));
+        assert_eq!(options.time, Some(DumpFormat::Json));
+
+        let options = parse(&["--tokens=json", "--time", "--ast=sexpr", "main.ong"]).unwrap();
+        assert_eq!(options.tokens, Some(DumpFormat::Json));
+        assert_eq!(options.time, Some(DumpFormat::Text));
+        assert_eq!(options.ast, Some(AstFormat::Sexpr));
+
+        let options = parse(&["--ast=json", "main.ong"]).unwrap();
+        assert_eq!(options.ast, Some(AstFormat::Json));
+
+        assert_eq!(
+            parse(&["--tokens=xml"]),
+            Err(CliError::InvalidValue {
+                flag: String::from("--tokens"),
+                value: String::from("xml")
+            })
+        );
+        assert_eq!(
+            parse(&["--ast=text"]),
+            Err(CliError::InvalidValue {
+                flag: String::from("--ast"),
+                value: String::from("text")
+            })
+        );
+    }
+
+    #[test]
+    fn eval_flags_accumulate_in_order() {
+        let options = parse(&["-e", "let x = 1;", "--eval", "x += 1;", "--ast"]).unwrap();
+
+        assert_eq!(options.eval, vec!["let x = 1;", "x += 1;"]);
+        assert_eq!(options.ast, Some(AstFormat::Debug));
+        assert_eq!(options.script, None);
+    }
+
+    #[test]
+    fn eval_errors() {
+        assert_eq!(
+            parse(&["-e"]),
+            Err(CliError::MissingValue(String::from("-e")))
+        );
+        assert_eq!(
+            parse(&["-e", "x;", "main.ong"]),
+            Err(CliError::UnexpectedArgument(String::from("main.ong")))
+        );
+    }
+
+    #[test]
+    fn other_flags() {
+        let options = parse(&["-h", "--version", "--no-semicolons"]).unwrap();
+
+        assert!(options.help && options.version && options.no_semicolons);
+    }
+}
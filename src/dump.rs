@@ -0,0 +1,827 @@
+use std::{
+    fmt::{self, Debug, Write},
+    time::Duration,
+};
+
+use orange::{
+    interner::{Interner, Symbol},
+    lexer::Token,
+    parser::{Expression, Literal, Statement},
+};
+
+/*
+ * one token per line: index, position, byte span, token type and lexeme (if the token
+ * carries one), the lexeme escaped so that a newline in a string stays on its line
+ */
+pub fn tokens(tokens: &[Token], source: &str) -> String {
+    let mut out = String::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        let token_type = token.token_type.to_string();
+        let position = format!("{}:{}", token.row, token.col);
+        let span = format!("{}..{}", token.start, token.end);
+
+        match token.value(source) {
+            Some(name) => writeln!(
+                out,
+                "{:>4}  {:<8} {:<10} {:<12} {}",
+                index,
+                position,
+                span,
+                token_type,
+                name.escape_debug()
+            ),
+            None => writeln!(
+                out,
+                "{:>4}  {:<8} {:<10} {}",
+                index, position, span, token_type
+            ),
+        }
+        .unwrap();
+    }
+
+    out
+}
+
+/*
+ * the token stream as a json array of {"index", "row", "col", "start", "end", "type", "lexeme"}
+ * objects, start and end being byte offsets into the source
+ * lexeme is null for tokens that don't carry one
+ */
+pub fn tokens_json(tokens: &[Token], source: &str) -> String {
+    let mut out = String::from("[");
+
+    for (index, token) in tokens.iter().enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+
+        let lexeme = match token.value(source) {
+            Some(name) => json_string(name),
+            None => String::from("null"),
+        };
+
+        write!(
+            out,
+            "\n  {{\"index\": {}, \"row\": {}, \"col\": {}, \"start\": {}, \"end\": {}, \"type\": {}, \"lexeme\": {}}}",
+            index,
+            token.row,
+            token.col,
+            token.start,
+            token.end,
+            json_string(&token.token_type.to_string()),
+            lexeme
+        )
+        .unwrap();
+    }
+
+    out.push_str("\n]\n");
+    out
+}
+
+/*
+ * the parsed program pretty-printed, one top-level statement after another
+ * the layout is that of {:#?}, with every symbol shown as the name it stands for
+ */
+pub fn ast(statements: &[Statement], interner: &Interner) -> String {
+    statements
+        .iter()
+        .map(|statement| format!("{:#?}\n", Named(statement, interner)))
+        .collect()
+}
+
+/*
+ * the parsed program as a json array with one object per top-level statement
+ * every node is an object whose "node" key names its kind, symbols are written as names
+ */
+pub fn ast_json(statements: &[Statement], interner: &Interner) -> String {
+    let mut out = String::from("[");
+
+    for (index, statement) in statements.iter().enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+
+        write!(out, "\n  {}", statement_json(statement, interner)).unwrap();
+    }
+
+    out.push_str("\n]\n");
+    out
+}
+
+fn statement_json(statement: &Statement, interner: &Interner) -> String {
+    let name = |symbol: &Symbol| json_string(interner.resolve(*symbol));
+    let json = |expression: &Expression| expression_json(expression, interner);
+    let block = |body: &[Statement]| {
+        let body: Vec<String> = body
+            .iter()
+            .map(|statement| statement_json(statement, interner))
+            .collect();
+        format!("[{}]", body.join(", "))
+    };
+
+    match statement {
+        Statement::Declaration {
+            variable_name,
+            expression,
+            constant,
+        } => format!(
+            "{{\"node\": \"Declaration\", \"variable_name\": {}, \"expression\": {}, \"constant\": {}}}",
+            name(variable_name),
+            json(expression),
+            constant
+        ),
+
+        Statement::Assignment {
+            variable_name,
+            expression,
+        } => format!(
+            "{{\"node\": \"Assignment\", \"variable_name\": {}, \"expression\": {}}}",
+            name(variable_name),
+            json(expression)
+        ),
+
+        Statement::Expression(expression) => format!(
+            "{{\"node\": \"Expression\", \"expression\": {}}}",
+            json(expression)
+        ),
+
+        Statement::For {
+            variable,
+            start,
+            end,
+            inclusive,
+            body,
+        } => format!(
+            "{{\"node\": \"For\", \"variable\": {}, \"start\": {}, \"end\": {}, \"inclusive\": {}, \"body\": {}}}",
+            name(variable),
+            json(start),
+            json(end),
+            inclusive,
+            block(body)
+        ),
+
+        Statement::While { condition, body } => format!(
+            "{{\"node\": \"While\", \"condition\": {}, \"body\": {}}}",
+            json(condition),
+            block(body)
+        ),
+
+        Statement::DoWhile { body, condition } => format!(
+            "{{\"node\": \"DoWhile\", \"body\": {}, \"condition\": {}}}",
+            block(body),
+            json(condition)
+        ),
+
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => format!(
+            "{{\"node\": \"If\", \"condition\": {}, \"then_branch\": {}, \"else_branch\": {}}}",
+            json(condition),
+            block(then_branch),
+            else_branch.as_deref().map_or(String::from("null"), block)
+        ),
+
+        Statement::Function {
+            name: function,
+            params,
+            body,
+        } => {
+            let params: Vec<String> = params.iter().map(name).collect();
+            format!(
+                "{{\"node\": \"Function\", \"name\": {}, \"params\": [{}], \"body\": {}}}",
+                name(function),
+                params.join(", "),
+                block(body)
+            )
+        }
+
+        Statement::Break => String::from("{\"node\": \"Break\"}"),
+        Statement::Continue => String::from("{\"node\": \"Continue\"}"),
+    }
+}
+
+fn expression_json(expression: &Expression, interner: &Interner) -> String {
+    let json = |expression: &Expression| expression_json(expression, interner);
+
+    match expression {
+        Expression::Literal(literal) => {
+            let value = match literal {
+                Literal::Number(value) if value.is_finite() => value.to_string(),
+                // json has no infinity, a literal too large for f64 is written as a string
+                Literal::Number(value) => json_string(&value.to_string()),
+                Literal::String(value) => json_string(value),
+                Literal::Char(value) => json_string(&value.to_string()),
+                Literal::Boolean(value) => value.to_string(),
+                Literal::Null => String::from("null"),
+            };
+
+            format!("{{\"node\": \"Literal\", \"value\": {}}}", value)
+        }
+
+        Expression::Variable(name) => format!(
+            "{{\"node\": \"Variable\", \"name\": {}}}",
+            json_string(interner.resolve(*name))
+        ),
+
+        Expression::Grouping(expression) => format!(
+            "{{\"node\": \"Grouping\", \"expression\": {}}}",
+            json(expression)
+        ),
+
+        Expression::Interpolation(parts) => {
+            let parts: Vec<String> = parts.iter().map(json).collect();
+            format!(
+                "{{\"node\": \"Interpolation\", \"parts\": [{}]}}",
+                parts.join(", ")
+            )
+        }
+
+        Expression::Unary { operator, rhs } => format!(
+            "{{\"node\": \"Unary\", \"operator\": {}, \"rhs\": {}}}",
+            json_string(&operator.to_string()),
+            json(rhs)
+        ),
+
+        Expression::Binary { lhs, operator, rhs } => format!(
+            "{{\"node\": \"Binary\", \"lhs\": {}, \"operator\": {}, \"rhs\": {}}}",
+            json(lhs),
+            json_string(&operator.to_string()),
+            json(rhs)
+        ),
+
+        Expression::Member {
+            object,
+            name,
+            optional,
+        } => format!(
+            "{{\"node\": \"Member\", \"object\": {}, \"name\": {}, \"optional\": {}}}",
+            json(object),
+            json_string(interner.resolve(*name)),
+            optional
+        ),
+
+        Expression::Pipe { value, function } => format!(
+            "{{\"node\": \"Pipe\", \"value\": {}, \"function\": {}}}",
+            json(value),
+            json(function)
+        ),
+    }
+}
+
+/*
+ * the parsed program as s-expressions, one top-level statement per line
+ * operators come first, (+ a (* b 2)), and blocks are written as (block ...)
+ */
+pub fn ast_sexpr(statements: &[Statement], interner: &Interner) -> String {
+    statements
+        .iter()
+        .map(|statement| format!("{}\n", statement_sexpr(statement, interner)))
+        .collect()
+}
+
+fn statement_sexpr(statement: &Statement, interner: &Interner) -> String {
+    let name = |symbol: &Symbol| interner.resolve(*symbol).to_string();
+    let show = |expression: &Expression| sexpr(expression, interner);
+    let block = |body: &[Statement]| {
+        let body: Vec<String> = body
+            .iter()
+            .map(|statement| format!(" {}", statement_sexpr(statement, interner)))
+            .collect();
+        format!("(block{})", body.concat())
+    };
+
+    match statement {
+        Statement::Declaration {
+            variable_name,
+            expression,
+            constant,
+        } => format!(
+            "({} {} {})",
+            if *constant { "const" } else { "let" },
+            name(variable_name),
+            show(expression)
+        ),
+
+        Statement::Assignment {
+            variable_name,
+            expression,
+        } => format!("(= {} {})", name(variable_name), show(expression)),
+
+        Statement::Expression(expression) => show(expression),
+
+        Statement::For {
+            variable,
+            start,
+            end,
+            inclusive,
+            body,
+        } => format!(
+            "(for {} ({} {} {}) {})",
+            name(variable),
+            if *inclusive { "..=" } else { ".." },
+            show(start),
+            show(end),
+            block(body)
+        ),
+
+        Statement::While { condition, body } => {
+            format!("(while {} {})", show(condition), block(body))
+        }
+
+        Statement::DoWhile { body, condition } => {
+            format!("(do-while {} {})", block(body), show(condition))
+        }
+
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => match else_branch {
+            Some(else_branch) => format!(
+                "(if {} {} {})",
+                show(condition),
+                block(then_branch),
+                block(else_branch)
+            ),
+            None => format!("(if {} {})", show(condition), block(then_branch)),
+        },
+
+        Statement::Function {
+            name: function,
+            params,
+            body,
+        } => {
+            let params: Vec<String> = params.iter().map(name).collect();
+            format!(
+                "(fn {} ({}) {})",
+                name(function),
+                params.join(" "),
+                block(body)
+            )
+        }
+
+        Statement::Break => String::from("break"),
+        Statement::Continue => String::from("continue"),
+    }
+}
+
+// the expression as an s-expression, operators first: (+ a (* b 2))
+fn sexpr(expression: &Expression, interner: &Interner) -> String {
+    match expression {
+        Expression::Literal(Literal::Number(value)) => value.to_string(),
+        Expression::Literal(Literal::String(value)) => format!("{:?}", value),
+        Expression::Literal(Literal::Char(value)) => format!("{:?}", value),
+        Expression::Literal(Literal::Boolean(value)) => value.to_string(),
+        Expression::Literal(Literal::Null) => String::from("null"),
+        Expression::Variable(name) => interner.resolve(*name).to_string(),
+        Expression::Grouping(inner) => format!("(group {})", sexpr(inner, interner)),
+        Expression::Interpolation(parts) => {
+            let parts: Vec<String> = parts.iter().map(|part| sexpr(part, interner)).collect();
+            format!("(interpolation {})", parts.join(" "))
+        }
+        Expression::Unary { operator, rhs } => {
+            format!("({} {})", operator, sexpr(rhs, interner))
+        }
+        Expression::Binary { lhs, operator, rhs } => format!(
+            "({} {} {})",
+            operator,
+            sexpr(lhs, interner),
+            sexpr(rhs, interner)
+        ),
+        Expression::Member {
+            object,
+            name,
+            optional,
+        } => format!(
+            "({} {} {})",
+            if *optional { "?." } else { "." },
+            sexpr(object, interner),
+            interner.resolve(*name)
+        ),
+        Expression::Pipe { value, function } => format!(
+            "(|> {} {})",
+            sexpr(value, interner),
+            sexpr(function, interner)
+        ),
+    }
+}
+
+// an AST node debug-printed with its symbols resolved through the interner
+struct Named<'a, T: ?Sized>(&'a T, &'a Interner);
+
+impl<'a, T: ?Sized> Named<'a, T> {
+    fn with<U: ?Sized>(&self, node: &'a U) -> Named<'a, U> {
+        Named(node, self.1)
+    }
+}
+
+impl Debug for Named<'_, Symbol> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.1.resolve(*self.0))
+    }
+}
+
+impl Debug for Named<'_, [Symbol]> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|symbol| self.with(symbol)))
+            .finish()
+    }
+}
+
+impl Debug for Named<'_, [Statement]> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|statement| self.with(statement)))
+            .finish()
+    }
+}
+
+impl Debug for Named<'_, [Expression]> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|expression| self.with(expression)))
+            .finish()
+    }
+}
+
+impl Debug for Named<'_, Statement> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Statement::Declaration {
+                variable_name,
+                expression,
+                constant,
+            } => f
+                .debug_struct("Declaration")
+                .field("variable_name", &self.with(variable_name))
+                .field("expression", &self.with(expression))
+                .field("constant", constant)
+                .finish(),
+
+            Statement::Assignment {
+                variable_name,
+                expression,
+            } => f
+                .debug_struct("Assignment")
+                .field("variable_name", &self.with(variable_name))
+                .field("expression", &self.with(expression))
+                .finish(),
+
+            Statement::Expression(expression) => f
+                .debug_tuple("Expression")
+                .field(&self.with(expression))
+                .finish(),
+
+            Statement::For {
+                variable,
+                start,
+                end,
+                inclusive,
+                body,
+            } => f
+                .debug_struct("For")
+                .field("variable", &self.with(variable))
+                .field("start", &self.with(start))
+                .field("end", &self.with(end))
+                .field("inclusive", inclusive)
+                .field("body", &self.with(body.as_slice()))
+                .finish(),
+
+            Statement::While { condition, body } => f
+                .debug_struct("While")
+                .field("condition", &self.with(condition))
+                .field("body", &self.with(body.as_slice()))
+                .finish(),
+
+            Statement::DoWhile { body, condition } => f
+                .debug_struct("DoWhile")
+                .field("body", &self.with(body.as_slice()))
+                .field("condition", &self.with(condition))
+                .finish(),
+
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => f
+                .debug_struct("If")
+                .field("condition", &self.with(condition))
+                .field("then_branch", &self.with(then_branch.as_slice()))
+                .field(
+                    "else_branch",
+                    &else_branch.as_deref().map(|branch| self.with(branch)),
+                )
+                .finish(),
+
+            Statement::Function { name, params, body } => f
+                .debug_struct("Function")
+                .field("name", &self.with(name))
+                .field("params", &self.with(params.as_slice()))
+                .field("body", &self.with(body.as_slice()))
+                .finish(),
+
+            Statement::Break => write!(f, "Break"),
+            Statement::Continue => write!(f, "Continue"),
+        }
+    }
+}
+
+impl Debug for Named<'_, Expression> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Expression::Literal(literal) => f.debug_tuple("Literal").field(literal).finish(),
+
+            Expression::Variable(name) => {
+                f.debug_tuple("Variable").field(&self.with(name)).finish()
+            }
+
+            Expression::Grouping(expression) => f
+                .debug_tuple("Grouping")
+                .field(&self.with(expression.as_ref()))
+                .finish(),
+
+            Expression::Interpolation(parts) => f
+                .debug_tuple("Interpolation")
+                .field(&self.with(parts.as_slice()))
+                .finish(),
+
+            Expression::Unary { operator, rhs } => f
+                .debug_struct("Unary")
+                .field("operator", operator)
+                .field("rhs", &self.with(rhs.as_ref()))
+                .finish(),
+
+            Expression::Binary { lhs, operator, rhs } => f
+                .debug_struct("Binary")
+                .field("lhs", &self.with(lhs.as_ref()))
+                .field("operator", operator)
+                .field("rhs", &self.with(rhs.as_ref()))
+                .finish(),
+
+            Expression::Member {
+                object,
+                name,
+                optional,
+            } => f
+                .debug_struct("Member")
+                .field("object", &self.with(object.as_ref()))
+                .field("name", &self.with(name))
+                .field("optional", optional)
+                .finish(),
+
+            Expression::Pipe { value, function } => f
+                .debug_struct("Pipe")
+                .field("value", &self.with(value.as_ref()))
+                .field("function", &self.with(function.as_ref()))
+                .finish(),
+        }
+    }
+}
+
+/*
+ * how many nodes the tree has, statements and expressions alike
+ * literals and names stored on a node don't count as nodes of their own
+ */
+pub fn node_count(statements: &[Statement]) -> usize {
+    statements.iter().map(statement_nodes).sum()
+}
+
+fn statement_nodes(statement: &Statement) -> usize {
+    1 + match statement {
+        Statement::Declaration { expression, .. }
+        | Statement::Assignment { expression, .. }
+        | Statement::Expression(expression) => expression_nodes(expression),
+        Statement::For {
+            start, end, body, ..
+        } => expression_nodes(start) + expression_nodes(end) + node_count(body),
+        Statement::While { condition, body } | Statement::DoWhile { body, condition } => {
+            expression_nodes(condition) + node_count(body)
+        }
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            expression_nodes(condition)
+                + node_count(then_branch)
+                + else_branch.as_deref().map_or(0, node_count)
+        }
+        Statement::Function { body, .. } => node_count(body),
+        Statement::Break | Statement::Continue => 0,
+    }
+}
+
+fn expression_nodes(expression: &Expression) -> usize {
+    1 + match expression {
+        Expression::Literal(_) | Expression::Variable(_) => 0,
+        Expression::Grouping(inner) => expression_nodes(inner),
+        Expression::Interpolation(parts) => parts.iter().map(expression_nodes).sum(),
+        Expression::Unary { rhs, .. } => expression_nodes(rhs),
+        Expression::Binary { lhs, rhs, .. } => expression_nodes(lhs) + expression_nodes(rhs),
+        Expression::Member { object, .. } => expression_nodes(object),
+        Expression::Pipe { value, function } => {
+            expression_nodes(value) + expression_nodes(function)
+        }
+    }
+}
+
+pub struct Phase {
+    pub name: &'static str,
+    pub duration: Duration,
+    pub counts: Vec<(&'static str, usize)>,
+}
+
+/*
+ * an aligned table with one row per phase: name, wall-clock time and counts
+ */
+pub fn phases(phases: &[Phase]) -> String {
+    let mut out = String::new();
+    writeln!(out, "{:<10} {:>12}  counts", "phase", "time").unwrap();
+
+    for phase in phases {
+        let counts = phase
+            .counts
+            .iter()
+            .map(|(name, count)| format!("{} {}", count, name))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        writeln!(
+            out,
+            "{:<10} {:>9.3} ms  {}",
+            phase.name,
+            phase.duration.as_secs_f64() * 1000.0,
+            counts
+        )
+        .unwrap();
+    }
+
+    out
+}
+
+/*
+ * the same table as a json object keyed by phase name
+ * every phase has "ms" plus one key per count
+ */
+pub fn phases_json(phases: &[Phase]) -> String {
+    let mut out = String::from("{");
+
+    for (index, phase) in phases.iter().enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+
+        write!(
+            out,
+            "\n  {}: {{\"ms\": {:.3}",
+            json_string(phase.name),
+            phase.duration.as_secs_f64() * 1000.0
+        )
+        .unwrap();
+
+        for (name, count) in &phase.counts {
+            write!(out, ", {}: {}", json_string(name), count).unwrap();
+        }
+
+        out.push('}');
+    }
+
+    out.push_str("\n}\n");
+    out
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use orange::prelude::*;
+
+    use super::*;
+
+    fn lex(source: &str) -> Vec<Token> {
+        let mut lexer = Lexer::from_source(source);
+        lexer.tokenize().unwrap();
+        lexer.tokens
+    }
+
+    #[test]
+    fn token_text() {
+        let source = "let x = \"a\\tb\nc\";";
+
+        assert_eq!(
+            tokens(&lex(source), source),
+            "   0  1:1      0..3       let\n   1  1:5      4..5       identifier   x\n   2  1:7      6..7       =\n   3  1:9      8..16      string       a\\tb\\nc\n   4  2:3      16..17     ;\n   5  2:4      17..17     EOF\n"
+        );
+    }
+
+    #[test]
+    fn token_json() {
+        let source = "x = \"a\\tb\";";
+
+        assert_eq!(
+            tokens_json(&lex(source), source),
+            "[\n  {\"index\": 0, \"row\": 1, \"col\": 1, \"start\": 0, \"end\": 1, \"type\": \"identifier\", \"lexeme\": \"x\"},\n  {\"index\": 1, \"row\": 1, \"col\": 3, \"start\": 2, \"end\": 3, \"type\": \"=\", \"lexeme\": null},\n  {\"index\": 2, \"row\": 1, \"col\": 5, \"start\": 4, \"end\": 10, \"type\": \"string\", \"lexeme\": \"a\\tb\"},\n  {\"index\": 3, \"row\": 1, \"col\": 11, \"start\": 10, \"end\": 11, \"type\": \";\", \"lexeme\": null},\n  {\"index\": 4, \"row\": 1, \"col\": 12, \"start\": 11, \"end\": 11, \"type\": \"EOF\", \"lexeme\": null}\n]\n"
+        );
+    }
+
+    // the statements that parsed from source, in the given format
+    fn printed(source: &str, format: fn(&[Statement], &Interner) -> String) -> String {
+        let mut lexer = Lexer::from_source(source);
+        lexer.tokenize().unwrap();
+
+        let tokens = mem::take(&mut lexer.tokens);
+        let interner = lexer.take_interner();
+        let mut parser = Parser::new(tokens, lexer.source(), interner);
+        let (statements, _) = parser.parse();
+
+        format(&statements, parser.interner())
+    }
+
+    #[test]
+    fn ast_shows_names() {
+        assert_eq!(
+            printed("let x = y;", ast),
+            "Declaration {\n    variable_name: \"x\",\n    expression: Variable(\n        \"y\",\n    ),\n    constant: false,\n}\n"
+        );
+    }
+
+    #[test]
+    fn ast_formats() {
+        let source = "let s = \"a\";\nwhile !done { s = s + 'b'; }";
+
+        assert_eq!(
+            printed(source, ast),
+            "Declaration {\n    variable_name: \"s\",\n    expression: Literal(\n        String(\n            \"a\",\n        ),\n    ),\n    constant: false,\n}\nWhile {\n    condition: Unary {\n        operator: Bang,\n        rhs: Variable(\n            \"done\",\n        ),\n    },\n    body: [\n        Assignment {\n            variable_name: \"s\",\n            expression: Binary {\n                lhs: Variable(\n                    \"s\",\n                ),\n                operator: Plus,\n                rhs: Literal(\n                    Char(\n                        'b',\n                    ),\n                ),\n            },\n        },\n    ],\n}\n"
+        );
+        assert_eq!(
+            printed(source, ast_json),
+            "[\n  {\"node\": \"Declaration\", \"variable_name\": \"s\", \"expression\": {\"node\": \"Literal\", \"value\": \"a\"}, \"constant\": false},\n  {\"node\": \"While\", \"condition\": {\"node\": \"Unary\", \"operator\": \"!\", \"rhs\": {\"node\": \"Variable\", \"name\": \"done\"}}, \"body\": [{\"node\": \"Assignment\", \"variable_name\": \"s\", \"expression\": {\"node\": \"Binary\", \"lhs\": {\"node\": \"Variable\", \"name\": \"s\"}, \"operator\": \"+\", \"rhs\": {\"node\": \"Literal\", \"value\": \"b\"}}}]}\n]\n"
+        );
+        assert_eq!(
+            printed(source, ast_sexpr),
+            "(let s \"a\")\n(while (! done) (block (= s (+ s 'b'))))\n"
+        );
+    }
+
+    #[test]
+    fn ast_formats_leave_out_statements_with_errors() {
+        let source = "let x = 1;\nlet y = ;\nif x { y = 2; }";
+
+        assert_eq!(
+            printed(source, ast_json),
+            "[\n  {\"node\": \"Declaration\", \"variable_name\": \"x\", \"expression\": {\"node\": \"Literal\", \"value\": 1}, \"constant\": false},\n  {\"node\": \"If\", \"condition\": {\"node\": \"Variable\", \"name\": \"x\"}, \"then_branch\": [{\"node\": \"Assignment\", \"variable_name\": \"y\", \"expression\": {\"node\": \"Literal\", \"value\": 2}}], \"else_branch\": null}\n]\n"
+        );
+        assert_eq!(
+            printed(source, ast_sexpr),
+            "(let x 1)\n(if x (block (= y 2)))\n"
+        );
+    }
+
+    #[test]
+    fn phase_json() {
+        let phases = [
+            Phase {
+                name: "lexing",
+                duration: Duration::from_micros(1500),
+                counts: vec![("tokens", 5), ("bytes", 10)],
+            },
+            Phase {
+                name: "parsing",
+                duration: Duration::ZERO,
+                counts: vec![("statements", 1)],
+            },
+        ];
+
+        assert_eq!(
+            phases_json(&phases),
+            "{\n  \"lexing\": {\"ms\": 1.500, \"tokens\": 5, \"bytes\": 10},\n  \"parsing\": {\"ms\": 0.000, \"statements\": 1}\n}\n"
+        );
+        assert_eq!(
+            super::phases(&phases),
+            "phase              time  counts\nlexing         1.500 ms  5 tokens, 10 bytes\nparsing        0.000 ms  1 statements\n"
+        );
+    }
+}
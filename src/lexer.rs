@@ -1,11 +1,34 @@
 use std::{
+    collections::VecDeque,
     fmt::{self, Display},
-    fs::read_to_string,
+    fs::File,
+    io::{self, BufReader, Read},
+    mem,
+    ops::Range,
+    path::Path,
 };
 
+use crate::interner::{Interner, Symbol};
+
+// sources larger than this are rejected unless a caller asks for a higher limit
+pub const DEFAULT_MAX_SOURCE_SIZE: u64 = 64 * 1024 * 1024;
+
+// LexError::render shows at most this many characters of the offending line
+const SNIPPET_WIDTH: usize = 80;
+
+const BYTE_ORDER_MARK: char = '\u{FEFF}';
+
+// bytes of source per token, used to size the token vector up front
+const AVERAGE_TOKEN_LENGTH: usize = 4;
+
+// tokenize gives up after this many errors, a binary file would otherwise produce one per byte
+pub const MAX_LEX_ERRORS: usize = 100;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum TokenType {
     Dot,
+    DotDot,
+    DotDotEqual,
     Comma,
     LeftParenthesis,
     RightParenthesis,
@@ -14,6 +37,8 @@ pub enum TokenType {
     LeftBrace,
     RightBrace,
     QuestionMark,
+    Colon,
+    ColonColon,
     Semicolon,
     Bang,
 
@@ -23,6 +48,13 @@ pub enum TokenType {
     Slash,
     Percent,
     Caret,
+    AsteriskAsterisk,
+
+    PlusEqual,
+    MinusEqual,
+    AsteriskEqual,
+    SlashEqual,
+    PercentEqual,
 
     If,
     While,
@@ -31,14 +63,22 @@ pub enum TokenType {
     For,
     In,
     Let,
+    Const,
     Function,
     Return,
+    Break,
+    Continue,
+    Match,
     Use,
 
     Identifier,
     Number,
     String,
+    Char,
     Boolean,
+    Null,
+    InterpolationStart,
+    InterpolationEnd,
 
     Equal,
     EqualEqual,
@@ -48,9 +88,25 @@ pub enum TokenType {
     LessEqual,
     BangEqual,
 
+    Arrow,
+    FatArrow,
+
     And,
     Or,
 
+    BitAnd,
+    BitOr,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
+
+    PipeGreater,
+    QuestionQuestion,
+    QuestionDot,
+
+    Comment,
+    DocComment,
+
     EOF,
 }
 
@@ -58,6 +114,8 @@ impl Display for TokenType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Dot => write!(f, "."),
+            Self::DotDot => write!(f, ".."),
+            Self::DotDotEqual => write!(f, "..="),
             Self::Comma => write!(f, ","),
             Self::LeftParenthesis => write!(f, "("),
             Self::RightParenthesis => write!(f, ")"),
@@ -66,6 +124,8 @@ impl Display for TokenType {
             Self::LeftBrace => write!(f, "{{"),
             Self::RightBrace => write!(f, "}}"),
             Self::QuestionMark => write!(f, "?"),
+            Self::Colon => write!(f, ":"),
+            Self::ColonColon => write!(f, "::"),
             Self::Semicolon => write!(f, ";"),
             Self::Bang => write!(f, "!"),
 
@@ -75,6 +135,13 @@ impl Display for TokenType {
             Self::Slash => write!(f, "/"),
             Self::Percent => write!(f, "%"),
             Self::Caret => write!(f, "^"),
+            Self::AsteriskAsterisk => write!(f, "**"),
+
+            Self::PlusEqual => write!(f, "+="),
+            Self::MinusEqual => write!(f, "-="),
+            Self::AsteriskEqual => write!(f, "*="),
+            Self::SlashEqual => write!(f, "/="),
+            Self::PercentEqual => write!(f, "%="),
 
             Self::If => write!(f, "if"),
             Self::While => write!(f, "while"),
@@ -83,14 +150,22 @@ impl Display for TokenType {
             Self::For => write!(f, "for"),
             Self::In => write!(f, "in"),
             Self::Let => write!(f, "let"),
+            Self::Const => write!(f, "const"),
             Self::Function => write!(f, "function"),
             Self::Return => write!(f, "return"),
+            Self::Break => write!(f, "break"),
+            Self::Continue => write!(f, "continue"),
+            Self::Match => write!(f, "match"),
             Self::Use => write!(f, "use"),
 
             Self::Identifier => write!(f, "identifier"),
             Self::Number => write!(f, "number"),
             Self::String => write!(f, "string"),
+            Self::Char => write!(f, "char"),
             Self::Boolean => write!(f, "boolean"),
+            Self::Null => write!(f, "null"),
+            Self::InterpolationStart => write!(f, "interpolation start"),
+            Self::InterpolationEnd => write!(f, "interpolation end"),
             Self::Equal => write!(f, "="),
             Self::EqualEqual => write!(f, "=="),
             Self::Greater => write!(f, ">"),
@@ -99,23 +174,272 @@ impl Display for TokenType {
             Self::LessEqual => write!(f, "<="),
             Self::BangEqual => write!(f, "!="),
 
+            Self::Arrow => write!(f, "->"),
+            Self::FatArrow => write!(f, "=>"),
+
             Self::And => write!(f, "&&"),
             Self::Or => write!(f, "||"),
 
+            Self::BitAnd => write!(f, "&"),
+            Self::BitOr => write!(f, "|"),
+            Self::BitNot => write!(f, "~"),
+            Self::ShiftLeft => write!(f, "<<"),
+            Self::ShiftRight => write!(f, ">>"),
+
+            Self::PipeGreater => write!(f, "|>"),
+            Self::QuestionQuestion => write!(f, "??"),
+            Self::QuestionDot => write!(f, "?."),
+
+            Self::Comment => write!(f, "comment"),
+            Self::DocComment => write!(f, "doc comment"),
+
             Self::EOF => write!(f, "EOF"),
         }
     }
 }
 
+/*
+ * start and end are byte offsets of the token's text in the lexer's source
+ * token_name only holds a value that can't be sliced out of the source:
+ * unescaped strings, chars, numbers written with separators or a radix prefix
+ * identifiers also carry their name interned in the lexer's interner
+ */
 #[derive(Clone, Debug)]
 pub struct Token {
     pub token_type: TokenType,
     pub token_name: Option<String>,
+    pub symbol: Option<Symbol>,
+    pub row: usize,
+    pub col: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Token {
+    pub(crate) fn new(
+        token_type: TokenType,
+        token_name: Option<String>,
+        (row, col): (usize, usize),
+        (start, end): (usize, usize),
+    ) -> Self {
+        Token {
+            token_type,
+            token_name,
+            symbol: None,
+            row,
+            col,
+            start,
+            end,
+        }
+    }
+
+    /*
+     * the byte range of the token's text in the source it was lexed from
+     * both ends fall on character boundaries, so the range can always slice the source
+     */
+    pub fn span(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    // the token's text exactly as written, source must be the one it was lexed from
+    pub fn lexeme<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.span()]
+    }
+
+    // the value of identifiers, literals, booleans and comments, None for operators and keywords
+    pub fn value<'a>(&'a self, source: &'a str) -> Option<&'a str> {
+        match (&self.token_name, &self.token_type) {
+            (Some(name), _) => Some(name),
+            (
+                None,
+                TokenType::Identifier | TokenType::Number | TokenType::Boolean | TokenType::Comment,
+            ) => Some(self.lexeme(source)),
+            // a doc comment's value is its text, without the ## and the space after it
+            (None, TokenType::DocComment) => {
+                let text = &self.lexeme(source)[2..];
+                Some(
+                    text.strip_prefix(' ')
+                        .unwrap_or(text)
+                        .trim_end_matches('\r'),
+                )
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LexErrorKind {
+    UnknownCharacter(char),
+    InvalidEscape(char),
+    UnterminatedString,
+    UnterminatedComment,
+    InvalidDigit { digit: char, radix: u32 },
+    MissingDigits { radix: u32 },
+    MalformedExponent,
+    MisplacedSeparator,
+    UnterminatedInterpolation,
+    EmptyChar,
+    MultipleCharsInChar,
+    UnterminatedChar,
+    StrayCarriageReturn,
+    MissingFraction,
+    MissingWholePart,
+    MultipleDecimalPoints,
+    InvalidNumber(String),
+    MisplacedByteOrderMark,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Display for LexErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // the code point tells apart characters that look alike or don't show at all
+            LexErrorKind::UnknownCharacter(c) if !c.is_ascii() => {
+                write!(f, "unknown character '{}' (U+{:04X})", c, *c as u32)
+            }
+            LexErrorKind::UnknownCharacter(c) => write!(f, "unknown character '{}'", c),
+            LexErrorKind::InvalidEscape(c) => {
+                write!(f, "invalid escape sequence '\\{}'", c.escape_debug())
+            }
+            LexErrorKind::UnterminatedString => write!(f, "unterminated string literal"),
+            LexErrorKind::UnterminatedComment => write!(f, "unterminated block comment"),
+            LexErrorKind::InvalidDigit { digit, radix } => {
+                write!(f, "invalid digit '{}' in base {} literal", digit, radix)
+            }
+            LexErrorKind::MissingDigits { radix } => {
+                write!(f, "base {} literal has no digits", radix)
+            }
+            LexErrorKind::MalformedExponent => write!(f, "exponent has no digits"),
+            LexErrorKind::UnterminatedInterpolation => {
+                write!(f, "unterminated '{{' in string interpolation")
+            }
+            LexErrorKind::EmptyChar => write!(f, "empty character literal"),
+            LexErrorKind::MultipleCharsInChar => {
+                write!(f, "character literal may only contain one character")
+            }
+            LexErrorKind::UnterminatedChar => write!(f, "unterminated character literal"),
+            LexErrorKind::MissingFraction => {
+                write!(f, "expected digits after the decimal point, as in 1.0")
+            }
+            LexErrorKind::MissingWholePart => {
+                write!(f, "expected a digit before the decimal point, as in 0.5")
+            }
+            LexErrorKind::MisplacedByteOrderMark => {
+                write!(
+                    f,
+                    "byte order mark (U+FEFF) is only allowed at the start of a file"
+                )
+            }
+            LexErrorKind::InvalidNumber(literal) => {
+                write!(f, "invalid numeric literal '{}'", literal)
+            }
+            LexErrorKind::MultipleDecimalPoints => {
+                write!(f, "number has more than one decimal point")
+            }
+            LexErrorKind::StrayCarriageReturn => {
+                write!(f, "carriage return not followed by a newline")
+            }
+            LexErrorKind::MisplacedSeparator => {
+                write!(f, "digit separator '_' must sit between two digits")
+            }
+        }
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at [{}, {}]", self.kind, self.row, self.col)
+    }
+}
+
+impl LexError {
+    // the error with the offending line of source underneath, see render_error
+    pub fn render(&self, source: &str, filename: &str) -> String {
+        render_error(&self.kind, self.row, self.col, source, filename)
+    }
+}
+
+/*
+ * an error in the usual compiler layout: message, file and position,
+ * then the offending line with a caret under the column
+ * tabs before the column are repeated in the caret line so the caret lines up,
+ * and a line longer than SNIPPET_WIDTH is cut down to a window around the column
+ */
+pub(crate) fn render_error(
+    message: &dyn Display,
+    row: usize,
+    col: usize,
+    source: &str,
+    filename: &str,
+) -> String {
+    let line: Vec<char> = source
+        .lines()
+        .nth(row - 1)
+        .unwrap_or_default()
+        .chars()
+        .collect();
+    let caret = (col - 1).min(line.len());
+
+    let (from, to) = if line.len() <= SNIPPET_WIDTH {
+        (0, line.len())
+    } else {
+        let from = caret
+            .saturating_sub(SNIPPET_WIDTH / 2)
+            .min(line.len() - SNIPPET_WIDTH);
+        (from, from + SNIPPET_WIDTH)
+    };
+
+    let mut text: String = line[from..to].iter().collect();
+    let mut padding: String = line[from..caret]
+        .iter()
+        .map(|&c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    if from > 0 {
+        text.insert_str(0, "...");
+        padding.insert_str(0, "   ");
+    }
+
+    if to < line.len() {
+        text.push_str("...");
+    }
+
+    let gutter = " ".repeat(row.to_string().len());
+
+    format!(
+        "error: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}^\n",
+        message, gutter, filename, row, col, gutter, row, text, gutter, padding
+    )
 }
 
 pub struct Lexer {
     pub tokens: Vec<Token>,
     source: String,
+    // byte offset of the next character to read
+    pos: usize,
+    // tokens already lexed but not yet handed out, a string interpolation yields several at once
+    pending: VecDeque<Token>,
+    // whether EOF has been handed out, see Lexer::finished
+    finished: bool,
+    // whether comments become Comment tokens instead of being skipped
+    comments: bool,
+    /*
+     * byte offset where the last token handed out ends, if member access can follow it
+     * a dot before digits is only member access right there, as in t.0
+     */
+    operand_end: Option<usize>,
+    interner: Interner,
+    /*
+     * position of the character being lexed, both counted from 1
+     * a column is one character whatever its width, so a tab counts as one column
+     */
     row: usize,
     col: usize,
 }
@@ -125,6 +449,12 @@ impl Default for Lexer {
         Self {
             tokens: Default::default(),
             source: Default::default(),
+            pos: 0,
+            pending: Default::default(),
+            finished: false,
+            comments: false,
+            operand_end: None,
+            interner: Interner::new(),
             row: 1,
             col: 1,
         }
@@ -132,207 +462,2082 @@ impl Default for Lexer {
 }
 
 impl Lexer {
-    pub fn new(source_path: &str) -> Self {
-        let src = read_to_string(source_path).expect("Error reading file.");
+    pub fn from_source(src: impl Into<String>) -> Self {
+        let source = src.into();
+
+        // a byte order mark left by some editors is skipped, spans still count its bytes
+        let pos = if source.starts_with(BYTE_ORDER_MARK) {
+            BYTE_ORDER_MARK.len_utf8()
+        } else {
+            0
+        };
 
         Lexer {
-            source: src,
+            source,
+            pos,
             ..Default::default()
         }
     }
 
-    pub fn tokenize(mut self) -> Self {
+    // keeps comments in the token stream, for tools like formatters that must not lose them
+    pub fn with_comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    // reads the file at source_path with the default size limit
+    pub fn from_file(source_path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_file_with_limit(source_path, DEFAULT_MAX_SOURCE_SIZE)
+    }
+
+    /*
+     * reads the file at source_path, refusing it if it is larger than max_source_size bytes
+     * the file's metadata is checked first so an oversized file is never read at all
+     */
+    pub fn from_file_with_limit(
+        source_path: impl AsRef<Path>,
+        max_source_size: u64,
+    ) -> io::Result<Self> {
+        let file = File::open(source_path)?;
+
+        if file.metadata()?.len() > max_source_size {
+            return Err(source_too_large(max_source_size));
+        }
+
+        Self::from_reader(BufReader::new(file), max_source_size)
+    }
+
+    /*
+     * reads the whole source from reader, counting the bytes as they come in
+     * so that inputs without a known size (pipes, stdin) obey the same limit
+     */
+    pub fn from_reader(reader: impl Read, max_source_size: u64) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader
+            .take(max_source_size.saturating_add(1))
+            .read_to_end(&mut bytes)?;
+
+        if bytes.len() as u64 > max_source_size {
+            return Err(source_too_large(max_source_size));
+        }
+
+        let src = String::from_utf8(bytes).map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "not valid UTF-8, bad byte at offset {}",
+                    error.utf8_error().valid_up_to()
+                ),
+            )
+        })?;
+
+        Ok(Self::from_source(src))
+    }
+
+    /*
+     * lexes the whole source, leaving every token in self.tokens
+     * lexing carries on past an error so that all of them are reported together,
+     * stopping early if there are more than MAX_LEX_ERRORS, see Lexer::finished
+     */
+    pub fn tokenize(&mut self) -> Result<(), Vec<LexError>> {
+        let mut errors = Vec::new();
+
+        // typical code averages a token every four to five bytes, whitespace included
+        self.tokens
+            .reserve(self.source.len() / AVERAGE_TOKEN_LENGTH);
+
+        loop {
+            match self.next_token() {
+                Ok(Some(token)) => self.tokens.push(token),
+                Ok(None) => break,
+                Err(error) => {
+                    // only the first MAX_LEX_ERRORS are kept, one more means giving up
+                    if errors.len() == MAX_LEX_ERRORS {
+                        break;
+                    }
+
+                    errors.push(error);
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    // whether the whole source was lexed, false after tokenize gave up on too many errors
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /*
+     * lexes just enough of the source to return the next token
+     * the last token is always EOF, after which None is returned
+     * after an error the offending text is skipped and the next call carries on behind it
+     */
+    pub fn next_token(&mut self) -> Result<Option<Token>, LexError> {
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                self.operand_end = matches!(
+                    token.token_type,
+                    TokenType::Identifier
+                        | TokenType::Number
+                        | TokenType::RightParenthesis
+                        | TokenType::RightBracket
+                )
+                .then_some(token.end);
+
+                return Ok(Some(token));
+            }
+
+            if self.finished {
+                return Ok(None);
+            }
+
+            let Some(current_char) = self.next_char() else {
+                self.finished = true;
+                return Ok(Some(Token::new(
+                    TokenType::EOF,
+                    None,
+                    (self.row, self.col),
+                    (self.pos, self.pos),
+                )));
+            };
+
+            if let Err(error) = self.scan(current_char) {
+                // scan returns before stepping past the last character it consumed
+                self.col += 1;
+                return Err(error);
+            }
+        }
+    }
+
+    // lexes the token starting at current_char, queueing whatever it produces
+    fn scan(&mut self, current_char: char) -> Result<(), LexError> {
+        // position and byte offset of the first character of the token being lexed
+        let start = (self.row, self.col);
+        let offset = self.pos - current_char.len_utf8();
+
+        // the token spans everything consumed since its first character
         macro_rules! push_token {
             ($type:expr, $name:expr) => {
-                self.tokens.push(Token {
-                    token_type: $type,
-                    token_name: $name,
-                })
+                self.pending
+                    .push_back(Token::new($type, $name, start, (offset, self.pos)))
             };
         }
 
-        let mut iter = self.source.chars().peekable();
-
-        while let Some(current_char) = iter.next() {
-            match current_char {
-                '.' => push_token!(TokenType::Dot, None),
-                ',' => push_token!(TokenType::Comma, None),
-                '(' => push_token!(TokenType::LeftParenthesis, None),
-                ')' => push_token!(TokenType::RightParenthesis, None),
-                '[' => push_token!(TokenType::LeftBracket, None),
-                ']' => push_token!(TokenType::RightBracket, None),
-                '{' => push_token!(TokenType::LeftBrace, None),
-                '}' => push_token!(TokenType::RightBrace, None),
-                '?' => push_token!(TokenType::QuestionMark, None),
-                ';' => push_token!(TokenType::Semicolon, None),
-                '+' => push_token!(TokenType::Plus, None),
-                '-' => push_token!(TokenType::Minus, None),
-                '*' => push_token!(TokenType::Asterisk, None),
-                '/' => push_token!(TokenType::Slash, None),
-                '%' => push_token!(TokenType::Percent, None),
-                '^' => push_token!(TokenType::Caret, None),
-
-                c if c == '"' => {
-                    let mut buffer = String::new();
-
-                    while let Some(cc) = iter.next() {
-                        self.col += 1;
-                        if cc != '"' {
-                            buffer.push(cc);
-                        } else {
+        match current_char {
+            ',' => push_token!(TokenType::Comma, None),
+            '(' => push_token!(TokenType::LeftParenthesis, None),
+            ')' => push_token!(TokenType::RightParenthesis, None),
+            '[' => push_token!(TokenType::LeftBracket, None),
+            ']' => push_token!(TokenType::RightBracket, None),
+            '{' => push_token!(TokenType::LeftBrace, None),
+            '}' => push_token!(TokenType::RightBrace, None),
+            ';' => push_token!(TokenType::Semicolon, None),
+            '^' => push_token!(TokenType::Caret, None),
+
+            /*
+             * a string containing {expression} regions becomes
+             * InterpolationStart, then its literal parts as String tokens and each
+             * expression's tokens wrapped in braces, then InterpolationEnd
+             * strings without interpolation stay a single String token
+             */
+            '"' => {
+                let mut buffer = String::new();
+                let mut terminated = false;
+                let mut parts: Vec<Token> = Vec::new();
+                let mut part_start = (self.row, self.col + 1);
+                let mut part_offset = self.pos;
+                // the first error inside the string, reported once the whole string is skipped
+                let mut error = None;
+
+                while let Some(cc) = self.next_char() {
+                    self.col += 1;
+
+                    match cc {
+                        '"' => {
+                            terminated = true;
                             break;
                         }
+
+                        '\\' => {
+                            let escape_col = self.col;
+
+                            let Some(mut escaped) = self.next_char() else {
+                                break;
+                            };
+
+                            if escaped == '\r' && self.peek_char() == Some('\n') {
+                                self.next_char();
+                                escaped = '\n';
+                            }
+
+                            match escape(escaped) {
+                                Some(value) => buffer.push(value),
+                                None => {
+                                    error.get_or_insert(LexError {
+                                        kind: LexErrorKind::InvalidEscape(escaped),
+                                        row: self.row,
+                                        col: escape_col,
+                                    });
+                                }
+                            }
+
+                            // a backslash can't escape a line break, but the line still ends
+                            if escaped == '\n' {
+                                self.row += 1;
+                                self.col = 0;
+                            } else {
+                                self.col += 1;
+                            }
+                        }
+
+                        '{' if matches!(self.peek_char(), Some('{')) => {
+                            buffer.push('{');
+                            self.next_char();
+                            self.col += 1;
+                        }
+
+                        '}' if matches!(self.peek_char(), Some('}')) => {
+                            buffer.push('}');
+                            self.next_char();
+                            self.col += 1;
+                        }
+
+                        '{' => {
+                            let brace = (self.row, self.col);
+                            let brace_offset = self.pos - 1;
+
+                            if !buffer.is_empty() {
+                                let text = mem::take(&mut buffer);
+                                parts.push(Token::new(
+                                    TokenType::String,
+                                    Some(text),
+                                    part_start,
+                                    (part_offset, brace_offset),
+                                ));
+                            }
+
+                            let Some(expression) = self.interpolated_source() else {
+                                return Err(LexError {
+                                    kind: LexErrorKind::UnterminatedInterpolation,
+                                    row: brace.0,
+                                    col: brace.1,
+                                });
+                            };
+
+                            // the embedded expression is lexed on its own, then moved to where it sits
+                            let mut tokens = Vec::new();
+                            let mut inner_lexer = Lexer::from_source(expression);
+
+                            // names inside the expression go into the same interner
+                            inner_lexer.interner = mem::take(&mut self.interner);
+
+                            for token in inner_lexer.by_ref() {
+                                match token {
+                                    Ok(token) => tokens.push(token),
+                                    Err(inner) => {
+                                        let (row, col) = relocate((inner.row, inner.col), brace);
+                                        error.get_or_insert(LexError { row, col, ..inner });
+                                    }
+                                }
+                            }
+
+                            self.interner = inner_lexer.interner;
+                            tokens.pop();
+
+                            parts.push(Token::new(
+                                TokenType::LeftBrace,
+                                None,
+                                brace,
+                                (brace_offset, brace_offset + 1),
+                            ));
+                            parts.extend(tokens.into_iter().map(|token| {
+                                let (row, col) = relocate((token.row, token.col), brace);
+                                let start = token.start + brace_offset + 1;
+                                let end = token.end + brace_offset + 1;
+                                Token {
+                                    row,
+                                    col,
+                                    start,
+                                    end,
+                                    ..token
+                                }
+                            }));
+                            parts.push(Token::new(
+                                TokenType::RightBrace,
+                                None,
+                                (self.row, self.col),
+                                (self.pos - 1, self.pos),
+                            ));
+
+                            part_start = (self.row, self.col + 1);
+                            part_offset = self.pos;
+                        }
+
+                        // strings may span lines, the newline is kept in the value as \n
+                        '\r' if self.peek_char() == Some('\n') => {}
+
+                        '\n' => {
+                            buffer.push(cc);
+                            self.row += 1;
+                            self.col = 0;
+                        }
+
+                        _ => buffer.push(cc),
                     }
+                }
+
+                if !terminated {
+                    return Err(LexError {
+                        kind: LexErrorKind::UnterminatedString,
+                        row: start.0,
+                        col: start.1,
+                    });
+                }
+
+                if let Some(error) = error {
+                    return Err(error);
+                }
 
+                if parts.is_empty() {
                     push_token!(TokenType::String, Some(buffer));
+                } else {
+                    if !buffer.is_empty() {
+                        parts.push(Token::new(
+                            TokenType::String,
+                            Some(buffer),
+                            part_start,
+                            (part_offset, self.pos - 1),
+                        ));
+                    }
+
+                    // the start and end tokens span the opening and closing quotes
+                    self.pending.push_back(Token::new(
+                        TokenType::InterpolationStart,
+                        None,
+                        start,
+                        (offset, offset + 1),
+                    ));
+                    self.pending.extend(parts);
+                    self.pending.push_back(Token::new(
+                        TokenType::InterpolationEnd,
+                        None,
+                        (self.row, self.col),
+                        (self.pos - 1, self.pos),
+                    ));
                 }
+            }
 
-                c if c.is_alphabetic() || c == '_' => {
-                    let mut buffer = String::new();
-                    buffer.push(c);
+            '\'' => {
+                let value = match self.next_char() {
+                    Some('\'') => {
+                        self.col += 1;
+                        return Err(LexError {
+                            kind: LexErrorKind::EmptyChar,
+                            row: start.0,
+                            col: start.1,
+                        });
+                    }
 
-                    while let Some(cc) = iter.peek() {
-                        if cc.is_alphanumeric() || *cc == '_' {
-                            buffer.push(*cc);
-                            iter.next();
-                            self.col += 1;
-                        } else {
-                            break;
+                    Some('\\') => {
+                        self.col += 1;
+                        let mut escaped = self.next_char();
+                        self.col += 1;
+
+                        if escaped == Some('\r') && self.peek_char() == Some('\n') {
+                            self.next_char();
+                            escaped = Some('\n');
+                        }
+
+                        match escaped.and_then(escape) {
+                            Some(value) => value,
+                            None => {
+                                let error = LexError {
+                                    kind: match escaped {
+                                        Some(other) => LexErrorKind::InvalidEscape(other),
+                                        None => LexErrorKind::UnterminatedChar,
+                                    },
+                                    row: self.row,
+                                    col: self.col - 1,
+                                };
+
+                                // the line ended after the backslash, and the literal with it
+                                if escaped == Some('\n') {
+                                    self.row += 1;
+                                    self.col = 0;
+                                    return Err(error);
+                                }
+
+                                // step over the closing quote so it doesn't open another literal
+                                if self.peek_char() == Some('\'') {
+                                    self.next_char();
+                                    self.col += 1;
+                                }
+
+                                return Err(error);
+                            }
                         }
                     }
 
-                    match buffer.as_str() {
-                        "let" => push_token!(TokenType::Let, None),
-                        "if" => push_token!(TokenType::If, None),
-                        "else" => push_token!(TokenType::Else, None),
-                        "while" => push_token!(TokenType::While, None),
-                        "do" => push_token!(TokenType::Do, None),
-                        "in" => push_token!(TokenType::In, None),
-                        "for" => push_token!(TokenType::For, None),
-                        "fn" => push_token!(TokenType::Function, None),
-                        "return" => push_token!(TokenType::Return, None),
-                        "use" => push_token!(TokenType::Use, None),
-                        "true" | "false" => push_token!(TokenType::Boolean, Some(buffer)),
-                        _ => push_token!(TokenType::Identifier, Some(buffer)),
+                    // a character literal can't run onto the next line
+                    Some('\n') => {
+                        self.row += 1;
+                        self.col = 0;
+                        return Err(LexError {
+                            kind: LexErrorKind::UnterminatedChar,
+                            row: start.0,
+                            col: start.1,
+                        });
                     }
-                }
 
-                c if c.is_numeric() => {
-                    let mut buffer = String::new();
-                    let mut seen_dot = false;
+                    Some(cc) => {
+                        self.col += 1;
+                        cc
+                    }
 
-                    buffer.push(c);
+                    None => {
+                        return Err(LexError {
+                            kind: LexErrorKind::UnterminatedChar,
+                            row: start.0,
+                            col: start.1,
+                        });
+                    }
+                };
 
-                    while let Some(&cc) = iter.peek() {
-                        if cc.is_numeric() {
-                            buffer.push(cc);
-                            iter.next();
-                            self.col += 1;
-                        } else if cc == '.' && !seen_dot {
-                            seen_dot = true;
-                            buffer.push(cc);
-                            iter.next();
+                match self.peek_char() {
+                    Some('\'') => {
+                        self.next_char();
+                        self.col += 1;
+                    }
+
+                    Some(cc) if cc != '\n' => {
+                        // skip the rest of the literal so its closing quote doesn't open another one
+                        while let Some(cc) = self.peek_char().filter(|cc| *cc != '\n') {
+                            self.next_char();
                             self.col += 1;
-                        } else {
-                            break;
+
+                            if cc == '\'' {
+                                break;
+                            }
                         }
+
+                        return Err(LexError {
+                            kind: LexErrorKind::MultipleCharsInChar,
+                            row: start.0,
+                            col: start.1,
+                        });
                     }
 
-                    push_token!(TokenType::Number, Some(buffer));
+                    _ => {
+                        return Err(LexError {
+                            kind: LexErrorKind::UnterminatedChar,
+                            row: start.0,
+                            col: start.1,
+                        });
+                    }
                 }
 
-                c if c == '!' => {
-                    if matches!(iter.peek(), Some(&'=')) {
-                        push_token!(TokenType::BangEqual, None);
-                        iter.next();
-                        self.col += 1;
-                    } else {
-                        push_token!(TokenType::Bang, None);
+                push_token!(TokenType::Char, Some(value.to_string()));
+            }
+
+            // raw strings copy everything up to the next quote, with no escapes or interpolation
+            'r' if matches!(self.peek_char(), Some('"')) => {
+                self.next_char();
+                self.col += 1;
+
+                let mut buffer = String::new();
+                let mut terminated = false;
+
+                while let Some(cc) = self.next_char() {
+                    self.col += 1;
+
+                    if cc == '"' {
+                        terminated = true;
+                        break;
+                    }
+
+                    if cc == '\r' && self.peek_char() == Some('\n') {
+                        continue;
+                    }
+
+                    if cc == '\n' {
+                        self.row += 1;
+                        self.col = 0;
                     }
+
+                    buffer.push(cc);
                 }
 
-                c if c == '=' => {
-                    if matches!(iter.peek(), Some(&'=')) {
-                        push_token!(TokenType::EqualEqual, None);
-                        iter.next();
+                if !terminated {
+                    return Err(LexError {
+                        kind: LexErrorKind::UnterminatedString,
+                        row: start.0,
+                        col: start.1,
+                    });
+                }
+
+                push_token!(TokenType::String, Some(buffer));
+            }
+
+            // identifiers and booleans are read back from the source through their span
+            c if is_identifier_start(c) => {
+                while let Some(cc) = self.peek_char() {
+                    if is_identifier_continue(cc) {
+                        self.next_char();
                         self.col += 1;
                     } else {
-                        push_token!(TokenType::Equal, None);
+                        break;
                     }
                 }
 
-                c if c == '>' => {
-                    if matches!(iter.peek(), Some(&'=')) {
-                        push_token!(TokenType::GreaterEqual, None);
-                        iter.next();
+                match &self.source[offset..self.pos] {
+                    "let" => push_token!(TokenType::Let, None),
+                    "const" => push_token!(TokenType::Const, None),
+                    "if" => push_token!(TokenType::If, None),
+                    "else" => push_token!(TokenType::Else, None),
+                    "while" => push_token!(TokenType::While, None),
+                    "do" => push_token!(TokenType::Do, None),
+                    "in" => push_token!(TokenType::In, None),
+                    "for" => push_token!(TokenType::For, None),
+                    "fn" => push_token!(TokenType::Function, None),
+                    "return" => push_token!(TokenType::Return, None),
+                    "break" => push_token!(TokenType::Break, None),
+                    "continue" => push_token!(TokenType::Continue, None),
+                    "match" => push_token!(TokenType::Match, None),
+                    "use" => push_token!(TokenType::Use, None),
+                    "true" | "false" => push_token!(TokenType::Boolean, None),
+                    "null" => push_token!(TokenType::Null, None),
+                    "and" => push_token!(TokenType::And, None),
+                    "or" => push_token!(TokenType::Or, None),
+                    "not" => push_token!(TokenType::Bang, None),
+                    name => {
+                        let mut token =
+                            Token::new(TokenType::Identifier, None, start, (offset, self.pos));
+                        token.symbol = Some(self.interner.intern(name));
+                        self.pending.push_back(token);
+                    }
+                }
+            }
+
+            // 0x, 0o and 0b literals are stored as their decimal value
+            '0' if matches!(self.peek_char(), Some('x' | 'X' | 'o' | 'O' | 'b' | 'B')) => {
+                let radix = match self.next_char().unwrap().to_ascii_lowercase() {
+                    'x' => 16,
+                    'o' => 8,
+                    _ => 2,
+                };
+                self.col += 1;
+
+                let mut value = 0.0;
+                let mut digit_count = 0;
+
+                while let Some(cc) = self.peek_char() {
+                    if cc == '_' {
+                        self.next_char();
                         self.col += 1;
-                    } else {
-                        push_token!(TokenType::Greater, None);
+
+                        if digit_count == 0 || !self.peek_char().is_some_and(|n| n.is_digit(radix))
+                        {
+                            return Err(LexError {
+                                kind: LexErrorKind::MisplacedSeparator,
+                                row: self.row,
+                                col: self.col,
+                            });
+                        }
+
+                        continue;
+                    }
+
+                    if !cc.is_alphanumeric() {
+                        break;
                     }
+
+                    let Some(digit) = cc.to_digit(radix) else {
+                        return Err(LexError {
+                            kind: LexErrorKind::InvalidDigit { digit: cc, radix },
+                            row: self.row,
+                            col: self.col + 1,
+                        });
+                    };
+
+                    value = value * radix as f64 + digit as f64;
+                    digit_count += 1;
+                    self.next_char();
+                    self.col += 1;
+                }
+
+                if digit_count == 0 {
+                    return Err(LexError {
+                        kind: LexErrorKind::MissingDigits { radix },
+                        row: start.0,
+                        col: start.1,
+                    });
                 }
 
-                c if c == '<' => {
-                    if matches!(iter.peek(), Some(&'=')) {
-                        push_token!(TokenType::LessEqual, None);
-                        iter.next();
+                push_token!(TokenType::Number, Some(value.to_string()));
+            }
+
+            c if c.is_ascii_digit() => {
+                let mut seen_dot = false;
+                let mut seen_separator = false;
+                let mut previous = c;
+
+                while let Some(cc) = self.peek_char() {
+                    if cc.is_ascii_digit() {
+                        self.next_char();
+                        self.col += 1;
+                    } else if cc == '.' && self.peek_second().is_some_and(|n| n.is_ascii_digit()) {
+                        // the dot is only part of the number when a digit follows it
+                        if seen_dot {
+                            return Err(LexError {
+                                kind: LexErrorKind::MultipleDecimalPoints,
+                                row: self.row,
+                                col: self.col + 1,
+                            });
+                        }
+
+                        seen_dot = true;
+                        self.next_char();
                         self.col += 1;
+                    } else if cc == '.'
+                        && !seen_dot
+                        && !self
+                            .peek_second()
+                            .is_some_and(|n| n == '.' || is_identifier_start(n))
+                    {
+                        // otherwise it starts a range (1..5) or a member (1.abs()), and 1. is written 1.0
+                        return Err(LexError {
+                            kind: LexErrorKind::MissingFraction,
+                            row: self.row,
+                            col: self.col + 1,
+                        });
+                    } else if cc == '_' {
+                        // separators are dropped from the value but must sit between two digits
+                        seen_separator = true;
+                        self.next_char();
+                        self.col += 1;
+
+                        if !previous.is_ascii_digit()
+                            || !self.peek_char().is_some_and(|n| n.is_ascii_digit())
+                        {
+                            return Err(LexError {
+                                kind: LexErrorKind::MisplacedSeparator,
+                                row: self.row,
+                                col: self.col,
+                            });
+                        }
                     } else {
-                        push_token!(TokenType::Less, None);
+                        break;
                     }
+
+                    previous = cc;
                 }
 
-                c if c.is_whitespace() => {
-                    if c == '\n' {
-                        self.row += 1;
-                        self.col = 0;
+                // optional exponent: e or E, an optional sign, then at least one digit
+                if matches!(self.peek_char(), Some('e' | 'E')) {
+                    let exponent_col = self.col + 1;
+
+                    self.next_char();
+                    self.col += 1;
+
+                    if matches!(self.peek_char(), Some('+' | '-')) {
+                        self.next_char();
+                        self.col += 1;
                     }
-                }
 
-                c if c == '&' => {
-                    if matches!(iter.peek(), Some(&'&')) {
-                        iter.next();
-                        iter.next();
-                        self.col += 2;
-                        push_token!(TokenType::And, None);
+                    let mut digit_count = 0;
+
+                    while self.peek_char().is_some_and(|cc| cc.is_ascii_digit()) {
+                        self.next_char();
+                        self.col += 1;
+                        digit_count += 1;
                     }
-                }
 
-                c if c == '|' => {
-                    if matches!(iter.peek(), Some(&'|')) {
-                        iter.next();
-                        iter.next();
-                        self.col += 2;
-                        push_token!(TokenType::Or, None);
+                    if digit_count == 0 {
+                        return Err(LexError {
+                            kind: LexErrorKind::MalformedExponent,
+                            row: self.row,
+                            col: exponent_col,
+                        });
                     }
                 }
 
-                '#' => {
-                    while let Some(c) = iter.next() {
+                // letters straight after a number, as in 1abc, are neither a number nor a name
+                if self.peek_char().is_some_and(is_identifier_start) {
+                    while self.peek_char().is_some_and(is_identifier_continue) {
+                        self.next_char();
                         self.col += 1;
-                        if c == '\n' {
-                            break;
-                        };
                     }
 
-                    self.row += 1;
-                    self.col = 0;
+                    return Err(LexError {
+                        kind: LexErrorKind::InvalidNumber(
+                            self.source[offset..self.pos].to_string(),
+                        ),
+                        row: start.0,
+                        col: start.1,
+                    });
                 }
 
-                _ => panic!(
-                    "  ::LEXER::  unknown token '{}' at [{}, {}]",
-                    current_char, self.row, self.col
-                ),
+                // only a number written with separators needs a value of its own
+                let value = seen_separator.then(|| self.source[offset..self.pos].replace('_', ""));
+                push_token!(TokenType::Number, value);
             }
 
-            self.col += 1;
-        }
+            '.' => {
+                if matches!(self.peek_char(), Some('.')) {
+                    self.next_char();
+                    self.col += 1;
 
-        push_token!(TokenType::EOF, None);
+                    if matches!(self.peek_char(), Some('=')) {
+                        self.next_char();
+                        self.col += 1;
+                        push_token!(TokenType::DotDotEqual, None);
+                    } else {
+                        push_token!(TokenType::DotDot, None);
+                    }
+                } else if self.peek_char().is_some_and(|n| n.is_ascii_digit())
+                    && self.operand_end != Some(offset)
+                {
+                    // a dot before digits is only member access, .5 on its own is written 0.5
+                    return Err(LexError {
+                        kind: LexErrorKind::MissingWholePart,
+                        row: start.0,
+                        col: start.1,
+                    });
+                } else {
+                    push_token!(TokenType::Dot, None);
+                }
+            }
 
-        return self;
-    }
+            '~' => push_token!(TokenType::BitNot, None),
 
-    pub fn _d(&self) {
-        dbg!(&self.tokens);
+            '?' => {
+                if matches!(self.peek_char(), Some('?')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::QuestionQuestion, None);
+                } else if matches!(self.peek_char(), Some('.'))
+                    && !self.peek_second().is_some_and(|n| n.is_ascii_digit())
+                {
+                    // ?.5 is a question mark then .5, as in a ?.5 : b
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::QuestionDot, None);
+                } else {
+                    push_token!(TokenType::QuestionMark, None);
+                }
+            }
+
+            ':' => {
+                if matches!(self.peek_char(), Some(':')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::ColonColon, None);
+                } else {
+                    push_token!(TokenType::Colon, None);
+                }
+            }
+
+            '+' => {
+                if matches!(self.peek_char(), Some('=')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::PlusEqual, None);
+                } else {
+                    push_token!(TokenType::Plus, None);
+                }
+            }
+
+            '-' => {
+                if matches!(self.peek_char(), Some('=')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::MinusEqual, None);
+                } else if matches!(self.peek_char(), Some('>')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::Arrow, None);
+                } else {
+                    push_token!(TokenType::Minus, None);
+                }
+            }
+
+            '*' => {
+                if matches!(self.peek_char(), Some('=')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::AsteriskEqual, None);
+                } else if matches!(self.peek_char(), Some('*')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::AsteriskAsterisk, None);
+                } else {
+                    push_token!(TokenType::Asterisk, None);
+                }
+            }
+
+            '/' => {
+                if matches!(self.peek_char(), Some('=')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::SlashEqual, None);
+                } else {
+                    push_token!(TokenType::Slash, None);
+                }
+            }
+
+            '%' => {
+                if matches!(self.peek_char(), Some('=')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::PercentEqual, None);
+                } else {
+                    push_token!(TokenType::Percent, None);
+                }
+            }
+
+            '!' => {
+                if matches!(self.peek_char(), Some('=')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::BangEqual, None);
+                } else {
+                    push_token!(TokenType::Bang, None);
+                }
+            }
+
+            '=' => {
+                if matches!(self.peek_char(), Some('=')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::EqualEqual, None);
+                } else if matches!(self.peek_char(), Some('>')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::FatArrow, None);
+                } else {
+                    push_token!(TokenType::Equal, None);
+                }
+            }
+
+            '>' => {
+                if matches!(self.peek_char(), Some('=')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::GreaterEqual, None);
+                } else if matches!(self.peek_char(), Some('>')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::ShiftRight, None);
+                } else {
+                    push_token!(TokenType::Greater, None);
+                }
+            }
+
+            '<' => {
+                if matches!(self.peek_char(), Some('=')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::LessEqual, None);
+                } else if matches!(self.peek_char(), Some('<')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::ShiftLeft, None);
+                } else {
+                    push_token!(TokenType::Less, None);
+                }
+            }
+
+            // \r\n counts as a single newline, a \r on its own is rejected
+            '\r' => {
+                if self.peek_char() != Some('\n') {
+                    return Err(LexError {
+                        kind: LexErrorKind::StrayCarriageReturn,
+                        row: self.row,
+                        col: self.col,
+                    });
+                }
+
+                self.next_char();
+                self.row += 1;
+                self.col = 0;
+            }
+
+            c if c.is_whitespace() => {
+                if c == '\n' {
+                    self.row += 1;
+                    self.col = 0;
+                }
+            }
+
+            '&' => {
+                if matches!(self.peek_char(), Some('&')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::And, None);
+                } else {
+                    push_token!(TokenType::BitAnd, None);
+                }
+            }
+
+            '|' => {
+                if matches!(self.peek_char(), Some('|')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::Or, None);
+                } else if matches!(self.peek_char(), Some('>')) {
+                    self.next_char();
+                    self.col += 1;
+                    push_token!(TokenType::PipeGreater, None);
+                } else {
+                    push_token!(TokenType::BitOr, None);
+                }
+            }
+
+            BYTE_ORDER_MARK => {
+                return Err(LexError {
+                    kind: LexErrorKind::MisplacedByteOrderMark,
+                    row: self.row,
+                    col: self.col,
+                });
+            }
+
+            // a #! line at the very start lets a script run directly, as in #!/usr/bin/env orange
+            '#' if self.source[..offset]
+                .trim_start_matches(BYTE_ORDER_MARK)
+                .is_empty()
+                && self.peek_char() == Some('!') =>
+            {
+                while let Some(c) = self.next_char() {
+                    if c == '\n' {
+                        self.row += 1;
+                        self.col = 0;
+                        break;
+                    }
+
+                    self.col += 1;
+                }
+            }
+
+            // block comments run from #[ to ]# and may nest
+            '#' if matches!(self.peek_char(), Some('[')) => {
+                self.next_char();
+                self.col += 1;
+
+                let mut depth = 1;
+
+                while depth > 0 {
+                    match self.next_char() {
+                        Some('#') if matches!(self.peek_char(), Some('[')) => {
+                            self.next_char();
+                            self.col += 2;
+                            depth += 1;
+                        }
+
+                        Some(']') if matches!(self.peek_char(), Some('#')) => {
+                            self.next_char();
+                            self.col += 2;
+                            depth -= 1;
+                        }
+
+                        Some('\n') => {
+                            self.row += 1;
+                            self.col = 0;
+                        }
+
+                        Some(_) => self.col += 1,
+
+                        None => {
+                            return Err(LexError {
+                                kind: LexErrorKind::UnterminatedComment,
+                                row: start.0,
+                                col: start.1,
+                            });
+                        }
+                    }
+                }
+
+                if self.comments {
+                    push_token!(TokenType::Comment, None);
+                }
+            }
+
+            /*
+             * the newline ending a line comment is left for the whitespace arm
+             * a comment starting with ## on a line of its own documents what follows it
+             * and is always kept, one DocComment token per line
+             */
+            '#' => {
+                let doc = self.peek_char() == Some('#')
+                    && self.source[..offset]
+                        .rsplit('\n')
+                        .next()
+                        .is_some_and(|before| before.trim().is_empty());
+
+                while self.peek_char().is_some_and(|c| c != '\n') {
+                    self.next_char();
+                    self.col += 1;
+                }
+
+                if doc {
+                    push_token!(TokenType::DocComment, None);
+                } else if self.comments {
+                    push_token!(TokenType::Comment, None);
+                }
+            }
+
+            _ => {
+                return Err(LexError {
+                    kind: LexErrorKind::UnknownCharacter(current_char),
+                    row: self.row,
+                    col: self.col,
+                });
+            }
+        }
+
+        self.col += 1;
+
+        Ok(())
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.source[self.pos..].chars().next()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.source[self.pos..].chars().nth(1)
+    }
+
+    /*
+     * collects the source of an interpolated expression, up to the '}' that closes it
+     * the lexer sits just after the opening '{'; braces nest and string literals inside
+     * are copied whole so their braces and quotes don't end the expression early
+     * returns None if the source ends first
+     */
+    fn interpolated_source(&mut self) -> Option<String> {
+        let mut source = String::new();
+        let mut depth = 0;
+        let mut in_string = false;
+
+        loop {
+            let c = self.next_char()?;
+            self.col += 1;
+
+            match c {
+                '\n' => {
+                    self.row += 1;
+                    self.col = 0;
+                }
+
+                '\\' if in_string => {
+                    source.push(c);
+                    source.push(self.next_char()?);
+                    self.col += 1;
+                    continue;
+                }
+
+                '"' => in_string = !in_string,
+                '{' if !in_string => depth += 1,
+                '}' if !in_string && depth == 0 => return Some(source),
+                '}' if !in_string => depth -= 1,
+                _ => {}
+            }
+
+            source.push(c);
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // the names of the identifiers lexed so far
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    // hands the interner over, to the parser for instance, leaving an empty one behind
+    pub fn take_interner(&mut self) -> Interner {
+        mem::take(&mut self.interner)
+    }
+
+    pub fn _d(&self) {
+        dbg!(&self.tokens);
+    }
+}
+
+// yields tokens and errors as they are lexed, ending after EOF
+impl Iterator for Lexer {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token().transpose()
+    }
+}
+
+/*
+ * identifiers start with '_' or an alphabetic character, in any script (café, 変数)
+ * and continue with those, numeric characters and combining marks, so an accent
+ * written as a separate code point stays part of the name
+ * digits can't start an identifier since they start a number
+ */
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || c.is_alphabetic()
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    is_identifier_start(c)
+        || c.is_numeric()
+        || matches!(
+            c,
+            '\u{0300}'..='\u{036F}'
+                | '\u{1AB0}'..='\u{1AFF}'
+                | '\u{1DC0}'..='\u{1DFF}'
+                | '\u{20D0}'..='\u{20FF}'
+                | '\u{FE20}'..='\u{FE2F}'
+        )
+}
+
+// the character an escape like \n stands for, given the character after the backslash
+fn escape(c: char) -> Option<char> {
+    match c {
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        '\\' => Some('\\'),
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        _ => None,
+    }
+}
+
+// maps a position inside an interpolated expression to its position in the whole source
+fn relocate((row, col): (usize, usize), brace: (usize, usize)) -> (usize, usize) {
+    if row == 1 {
+        (brace.0, brace.1 + col)
+    } else {
+        (brace.0 + row - 1, col)
+    }
+}
+
+fn source_too_large(max_source_size: u64) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::FileTooLarge,
+        format!("source is larger than the {} byte limit", max_source_size),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn lex(source: &str) -> Vec<Token> {
+        let mut lexer = Lexer::from_source(source);
+        lexer.tokenize().unwrap();
+        lexer.tokens
+    }
+
+    fn types(source: &str) -> Vec<TokenType> {
+        lex(source)
+            .into_iter()
+            .map(|token| token.token_type)
+            .collect()
+    }
+
+    // the value of every token that has one, in order
+    fn values(source: &str) -> Vec<String> {
+        lex(source)
+            .iter()
+            .filter_map(|token| token.value(source).map(str::to_string))
+            .collect()
+    }
+
+    fn positions(source: &str) -> Vec<(usize, usize)> {
+        lex(source)
+            .iter()
+            .map(|token| (token.row, token.col))
+            .collect()
+    }
+
+    fn errors(source: &str) -> Vec<LexError> {
+        Lexer::from_source(source).tokenize().unwrap_err()
+    }
+
+    fn error(source: &str) -> (LexErrorKind, usize, usize) {
+        let error = errors(source).remove(0);
+        (error.kind, error.row, error.col)
+    }
+
+    #[test]
+    fn unknown_character_is_reported_with_its_position() {
+        assert_eq!(
+            error("let x = 1;\nlet y = @;"),
+            (LexErrorKind::UnknownCharacter('@'), 2, 9)
+        );
+    }
+
+    #[test]
+    fn tokens_record_the_position_of_their_first_character() {
+        let source = "let name = 12.5;\nif a == b && c {\n  x = \"str\";\n}";
+
+        assert_eq!(
+            positions(source),
+            vec![
+                (1, 1),
+                (1, 5),
+                (1, 10),
+                (1, 12),
+                (1, 16),
+                (2, 1),
+                (2, 4),
+                (2, 6),
+                (2, 9),
+                (2, 11),
+                (2, 14),
+                (2, 16),
+                (3, 3),
+                (3, 5),
+                (3, 7),
+                (3, 12),
+                (4, 1),
+                (4, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn string_escapes_are_translated() {
+        assert_eq!(
+            values(r#"x = "say \"hi\" now\\\n\t\r" + y;"#),
+            vec!["x", "say \"hi\" now\\\n\t\r", "y"]
+        );
+    }
+
+    #[test]
+    fn invalid_escape_is_reported_and_the_string_skipped() {
+        let errors = errors(r#"x = "a\qb"; y = @;"#);
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            (errors[0].kind.clone(), errors[0].row, errors[0].col),
+            (LexErrorKind::InvalidEscape('q'), 1, 7)
+        );
+        assert_eq!(errors[1].kind, LexErrorKind::UnknownCharacter('@'));
+    }
+
+    #[test]
+    fn escaped_line_break_still_ends_the_line() {
+        let found: Vec<(LexErrorKind, usize, usize)> = errors("let s = \"a\\\nb\"; @")
+            .into_iter()
+            .map(|error| (error.kind, error.row, error.col))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (LexErrorKind::InvalidEscape('\n'), 1, 11),
+                (LexErrorKind::UnknownCharacter('@'), 2, 5),
+            ]
+        );
+
+        let crlf = errors("let s = \"a\\\r\nb\"; @");
+        assert_eq!(crlf[0].kind, LexErrorKind::InvalidEscape('\n'));
+        assert_eq!((crlf[1].row, crlf[1].col), (2, 5));
+
+        let char_literal = errors("x = '\\\n; @");
+        assert_eq!((char_literal[0].row, char_literal[0].col), (1, 6));
+        assert_eq!((char_literal[1].row, char_literal[1].col), (2, 3));
+    }
+
+    #[test]
+    fn invalid_escape_message_escapes_control_characters() {
+        assert_eq!(
+            LexErrorKind::InvalidEscape('\n').to_string(),
+            "invalid escape sequence '\\\\n'"
+        );
+        assert_eq!(
+            LexErrorKind::InvalidEscape('q').to_string(),
+            "invalid escape sequence '\\q'"
+        );
+    }
+
+    #[test]
+    fn unterminated_string_points_at_its_opening_quote() {
+        assert_eq!(
+            error("let x = \"abc"),
+            (LexErrorKind::UnterminatedString, 1, 9)
+        );
+    }
+
+    #[test]
+    fn block_comments_span_lines_and_nest() {
+        let source = "a #[ one\ntwo #[ nested ]#\nthree ]# b\nc";
+
+        assert_eq!(
+            types(source),
+            vec![
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+        assert_eq!(positions(source), vec![(1, 1), (3, 10), (4, 1), (4, 2)]);
+    }
+
+    #[test]
+    fn unterminated_block_comment_points_at_its_opening() {
+        assert_eq!(
+            error("x\n  #[ open #[ ]#\n"),
+            (LexErrorKind::UnterminatedComment, 2, 3)
+        );
+    }
+
+    #[test]
+    fn radix_literals_hold_their_decimal_value() {
+        assert_eq!(
+            values("0x1F 0o755 0b1010 0XFF 0B1 0xf_f"),
+            vec!["31", "493", "10", "255", "1", "255"]
+        );
+    }
+
+    #[test]
+    fn radix_literal_errors() {
+        assert_eq!(
+            error("x = 0b102;"),
+            (
+                LexErrorKind::InvalidDigit {
+                    digit: '2',
+                    radix: 2
+                },
+                1,
+                9
+            )
+        );
+        assert_eq!(
+            error("0x;"),
+            (LexErrorKind::MissingDigits { radix: 16 }, 1, 1)
+        );
+    }
+
+    #[test]
+    fn exponents() {
+        assert_eq!(values("1e3 2.5E-3 7e+2"), vec!["1e3", "2.5E-3", "7e+2"]);
+        assert_eq!(error("1e;"), (LexErrorKind::MalformedExponent, 1, 2));
+        assert_eq!(error("1e+;"), (LexErrorKind::MalformedExponent, 1, 2));
+    }
+
+    #[test]
+    fn digit_separators_are_dropped_from_the_value() {
+        assert_eq!(values("1_0.5_5 1_000 15"), vec!["10.55", "1000", "15"]);
+        assert_eq!(error("1__0"), (LexErrorKind::MisplacedSeparator, 1, 2));
+        assert_eq!(error("10_;"), (LexErrorKind::MisplacedSeparator, 1, 3));
+        assert_eq!(error("1_.5"), (LexErrorKind::MisplacedSeparator, 1, 2));
+    }
+
+    #[test]
+    fn only_ascii_digits_make_numbers() {
+        assert_eq!(
+            error("let x = ½;"),
+            (LexErrorKind::UnknownCharacter('½'), 1, 9)
+        );
+        assert_eq!(
+            error("let x = ٣;"),
+            (LexErrorKind::UnknownCharacter('٣'), 1, 9)
+        );
+        assert_eq!(
+            error("let x = 1٣;"),
+            (LexErrorKind::UnknownCharacter('٣'), 1, 10)
+        );
+        assert_eq!(values("x٣"), vec!["x٣"]);
+    }
+
+    #[test]
+    fn compound_assignment_operators() {
+        assert_eq!(
+            types("+= -= *= /= %= + - * / %"),
+            vec![
+                TokenType::PlusEqual,
+                TokenType::MinusEqual,
+                TokenType::AsteriskEqual,
+                TokenType::SlashEqual,
+                TokenType::PercentEqual,
+                TokenType::Plus,
+                TokenType::Minus,
+                TokenType::Asterisk,
+                TokenType::Slash,
+                TokenType::Percent,
+                TokenType::EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn arrows() {
+        assert_eq!(
+            types("a->b x => y"),
+            vec![
+                TokenType::Identifier,
+                TokenType::Arrow,
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::FatArrow,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+
+        // with a space in between they stay two operators
+        assert_eq!(
+            types("a - > b"),
+            vec![
+                TokenType::Identifier,
+                TokenType::Minus,
+                TokenType::Greater,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn ranges_are_not_mistaken_for_floats() {
+        assert_eq!(
+            types("0..10 0..=10"),
+            vec![
+                TokenType::Number,
+                TokenType::DotDot,
+                TokenType::Number,
+                TokenType::Number,
+                TokenType::DotDotEqual,
+                TokenType::Number,
+                TokenType::EOF
+            ]
+        );
+        assert_eq!(values("1..10 1.5..2.5"), vec!["1", "10", "1.5", "2.5"]);
+    }
+
+    #[test]
+    fn colons() {
+        assert_eq!(
+            types("a: b mod::item"),
+            vec![
+                TokenType::Identifier,
+                TokenType::Colon,
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::ColonColon,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn bitwise_operators() {
+        assert_eq!(
+            types("a & b | c ~d 1 << 3 >> 2 <= >="),
+            vec![
+                TokenType::Identifier,
+                TokenType::BitAnd,
+                TokenType::Identifier,
+                TokenType::BitOr,
+                TokenType::Identifier,
+                TokenType::BitNot,
+                TokenType::Identifier,
+                TokenType::Number,
+                TokenType::ShiftLeft,
+                TokenType::Number,
+                TokenType::ShiftRight,
+                TokenType::Number,
+                TokenType::LessEqual,
+                TokenType::GreaterEqual,
+                TokenType::EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn lone_ampersand_and_bar_are_bitwise_operators() {
+        assert_eq!(
+            types("a & b"),
+            vec![
+                TokenType::Identifier,
+                TokenType::BitAnd,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+
+        // nothing follows them to peek at, but they are still kept
+        assert_eq!(
+            types("a &"),
+            vec![TokenType::Identifier, TokenType::BitAnd, TokenType::EOF]
+        );
+        assert_eq!(
+            types("a |"),
+            vec![TokenType::Identifier, TokenType::BitOr, TokenType::EOF]
+        );
+        assert_eq!(positions("a |"), vec![(1, 1), (1, 3), (1, 4)]);
+    }
+
+    #[test]
+    fn keywords() {
+        assert_eq!(
+            types("break continue null match const true false fn do"),
+            vec![
+                TokenType::Break,
+                TokenType::Continue,
+                TokenType::Null,
+                TokenType::Match,
+                TokenType::Const,
+                TokenType::Boolean,
+                TokenType::Boolean,
+                TokenType::Function,
+                TokenType::Do,
+                TokenType::EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn word_operators_lex_like_their_symbols() {
+        assert_eq!(types("a and not b or c"), types("a && !b || c"));
+    }
+
+    #[test]
+    fn plain_strings_stay_a_single_token() {
+        let source = r#""plain {{braces}}""#;
+
+        assert_eq!(types(source), vec![TokenType::String, TokenType::EOF]);
+        assert_eq!(values(source), vec!["plain {braces}"]);
+    }
+
+    #[test]
+    fn interpolation_yields_its_parts_in_order() {
+        let source = r#""a {x + 1} b {y}""#;
+
+        assert_eq!(
+            types(source),
+            vec![
+                TokenType::InterpolationStart,
+                TokenType::String,
+                TokenType::LeftBrace,
+                TokenType::Identifier,
+                TokenType::Plus,
+                TokenType::Number,
+                TokenType::RightBrace,
+                TokenType::String,
+                TokenType::LeftBrace,
+                TokenType::Identifier,
+                TokenType::RightBrace,
+                TokenType::InterpolationEnd,
+                TokenType::EOF
+            ]
+        );
+        assert_eq!(values(source), vec!["a ", "x", "1", " b ", "y"]);
+
+        // tokens inside the braces point at their place in the whole source
+        let tokens = lex(source);
+        assert_eq!((tokens[3].row, tokens[3].col), (1, 5));
+        assert_eq!(tokens[3].lexeme(source), "x");
+    }
+
+    #[test]
+    fn unterminated_interpolation_points_at_its_brace() {
+        assert_eq!(
+            error(r#"x = "a {b"#),
+            (LexErrorKind::UnterminatedInterpolation, 1, 8)
+        );
+    }
+
+    #[test]
+    fn raw_strings_are_copied_verbatim() {
+        assert_eq!(
+            values(r##"r"a\nb" r"# not a comment""##),
+            vec![r"a\nb", "# not a comment"]
+        );
+    }
+
+    #[test]
+    fn char_literals() {
+        assert_eq!(values(r"'a' '\n' '\''"), vec!["a", "\n", "'"]);
+        assert_eq!(
+            types("'a' == 'b'"),
+            vec![
+                TokenType::Char,
+                TokenType::EqualEqual,
+                TokenType::Char,
+                TokenType::EOF
+            ]
+        );
+        assert_eq!(error("x = '';"), (LexErrorKind::EmptyChar, 1, 5));
+        assert_eq!(
+            error("x = 'ab';"),
+            (LexErrorKind::MultipleCharsInChar, 1, 5)
+        );
+    }
+
+    #[test]
+    fn tokens_after_a_multiline_string_have_the_right_position() {
+        let source = "x = \"one\ntwo\nthree\"; y";
+        let tokens = lex(source);
+
+        assert_eq!(tokens[2].value(source), Some("one\ntwo\nthree"));
+        assert_eq!((tokens[3].row, tokens[3].col), (3, 7));
+        assert_eq!((tokens[4].row, tokens[4].col), (3, 9));
+    }
+
+    #[test]
+    fn iterator_matches_tokenize() {
+        for source in [include_str!("../samples/nocap.ong"), "let s = \"a{b}c\";"] {
+            let iterated: Vec<Token> = Lexer::from_source(source).map(Result::unwrap).collect();
+            let tokenized = lex(source);
+
+            assert_eq!(iterated.len(), tokenized.len());
+
+            for (a, b) in iterated.iter().zip(&tokenized) {
+                assert_eq!(
+                    (&a.token_type, a.span(), a.row, a.col),
+                    (&b.token_type, b.span(), b.row, b.col)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn iterator_yields_one_eof_then_none() {
+        let mut lexer = Lexer::from_source("x");
+
+        assert_eq!(
+            lexer.next().unwrap().unwrap().token_type,
+            TokenType::Identifier
+        );
+        assert_eq!(lexer.next().unwrap().unwrap().token_type, TokenType::EOF);
+        assert!(lexer.next().is_none());
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn only_escaped_or_separated_values_are_owned() {
+        let tokens = lex(r#"name 12 1_2 "s""#);
+        let owned: Vec<bool> = tokens
+            .iter()
+            .map(|token| token.token_name.is_some())
+            .collect();
+
+        assert_eq!(owned, vec![false, false, true, true, false]);
+    }
+
+    #[test]
+    fn errors_are_collected_in_one_pass() {
+        let errors = errors("a @ b\nc $ d\ne ` f");
+        let found: Vec<(LexErrorKind, usize, usize)> = errors
+            .into_iter()
+            .map(|error| (error.kind, error.row, error.col))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (LexErrorKind::UnknownCharacter('@'), 1, 3),
+                (LexErrorKind::UnknownCharacter('$'), 2, 3),
+                (LexErrorKind::UnknownCharacter('`'), 3, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn error_collection_is_capped() {
+        let mut lexer = Lexer::from_source("@".repeat(MAX_LEX_ERRORS + 1));
+        assert_eq!(lexer.tokenize().unwrap_err().len(), MAX_LEX_ERRORS);
+        assert!(!lexer.finished());
+
+        let mut lexer = Lexer::from_source("@".repeat(MAX_LEX_ERRORS));
+        assert_eq!(lexer.tokenize().unwrap_err().len(), MAX_LEX_ERRORS);
+        assert!(lexer.finished());
+    }
+
+    #[test]
+    fn shebang_line_is_skipped() {
+        let tokens = lex("#!/usr/bin/env orange\nlet x = 1;");
+
+        assert_eq!(tokens[0].token_type, TokenType::Let);
+        assert_eq!((tokens[0].row, tokens[0].col), (2, 1));
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        assert_eq!(
+            values("café 変数 x1 _y2 e\u{301}"),
+            vec!["café", "変数", "x1", "_y2", "e\u{301}"]
+        );
+        assert_eq!(
+            error("let 🍊 = 1;"),
+            (LexErrorKind::UnknownCharacter('🍊'), 1, 5)
+        );
+    }
+
+    #[test]
+    fn logical_operators_keep_both_operands() {
+        let expected = vec![
+            TokenType::Identifier,
+            TokenType::And,
+            TokenType::Identifier,
+            TokenType::EOF,
+        ];
+
+        assert_eq!(types("a&&b"), expected);
+        assert_eq!(types("a && b"), expected);
+        assert_eq!(types("x||y"), types("x || y"));
+        assert_eq!(values("x||y"), vec!["x", "y"]);
+    }
+
+    #[test]
+    fn columns_are_exact_after_operators_and_tabs() {
+        let source = "a <= b && c\n\tx += y ** 2";
+
+        assert_eq!(
+            positions(source),
+            vec![
+                (1, 1),
+                (1, 3),
+                (1, 6),
+                (1, 8),
+                (1, 11),
+                (2, 2),
+                (2, 4),
+                (2, 7),
+                (2, 9),
+                (2, 12),
+                (2, 13),
+            ]
+        );
+    }
+
+    #[test]
+    fn crlf_counts_as_one_newline() {
+        let lf = "let a = 1;\nlet b = \"x\ny\";\nc";
+
+        assert_eq!(positions(lf), positions(&lf.replace('\n', "\r\n")));
+        assert_eq!(values(&lf.replace('\n', "\r\n"))[3], "x\ny");
+        assert_eq!(error("a\rb"), (LexErrorKind::StrayCarriageReturn, 1, 2));
+    }
+
+    #[test]
+    fn decimal_point_forms() {
+        assert_eq!(error("x = 1.;"), (LexErrorKind::MissingFraction, 1, 6));
+        assert_eq!(error("x = .5;"), (LexErrorKind::MissingWholePart, 1, 5));
+        assert_eq!(error("return .5;"), (LexErrorKind::MissingWholePart, 1, 8));
+        assert_eq!(
+            error("a # note\n.5"),
+            (LexErrorKind::MissingWholePart, 2, 1)
+        );
+        assert_eq!(
+            error("x = 1.5.2;"),
+            (LexErrorKind::MultipleDecimalPoints, 1, 8)
+        );
+        assert_eq!(
+            types("x.y"),
+            vec![
+                TokenType::Identifier,
+                TokenType::Dot,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+        assert_eq!(
+            types("t.0"),
+            vec![
+                TokenType::Identifier,
+                TokenType::Dot,
+                TokenType::Number,
+                TokenType::EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn a_dot_before_a_name_is_left_to_member_access() {
+        assert_eq!(
+            types("3.e"),
+            vec![
+                TokenType::Number,
+                TokenType::Dot,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_are_kept_on_request() {
+        let source = "a # one\nb #[ two ]# c\n# three";
+        let mut lexer = Lexer::from_source(source).with_comments(true);
+        lexer.tokenize().unwrap();
+
+        let comments: Vec<(usize, &str)> = lexer
+            .tokens
+            .iter()
+            .filter(|token| token.token_type == TokenType::Comment)
+            .map(|token| (token.row, token.lexeme(source)))
+            .collect();
+
+        assert_eq!(
+            comments,
+            vec![(1, "# one"), (2, "#[ two ]#"), (3, "# three")]
+        );
+        assert_eq!(
+            types(source),
+            vec![
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_slice_the_source() {
+        let source = "let café = \"ü\" + 変数;";
+        let lexemes: Vec<&str> = lex(source)
+            .iter()
+            .map(|token| token.lexeme(source))
+            .collect();
+
+        assert_eq!(
+            lexemes,
+            vec!["let", "café", "=", "\"ü\"", "+", "変数", ";", ""]
+        );
+    }
+
+    #[test]
+    fn render_points_at_the_column() {
+        let error = LexError {
+            kind: LexErrorKind::UnknownCharacter('@'),
+            row: 2,
+            col: 5,
+        };
+
+        assert_eq!(
+            error.render("x;\nlet @", "main.ong"),
+            "error: unknown character '@'\n --> main.ong:2:5\n  |\n2 | let @\n  |     ^\n"
+        );
+    }
+
+    #[test]
+    fn render_keeps_tabs_in_front_of_the_caret() {
+        let error = LexError {
+            kind: LexErrorKind::UnknownCharacter('@'),
+            row: 1,
+            col: 3,
+        };
+
+        assert_eq!(
+            error.render("\t\t@\n", "<eval>"),
+            "error: unknown character '@'\n --> <eval>:1:3\n  |\n1 | \t\t@\n  | \t\t^\n"
+        );
+    }
+
+    #[test]
+    fn render_truncates_long_lines() {
+        let line = format!("{}@{}", "a".repeat(100), "b".repeat(100));
+        let error = LexError {
+            kind: LexErrorKind::UnknownCharacter('@'),
+            row: 1,
+            col: 101,
+        };
+
+        let rendered = error.render(&line, "long.ong");
+        let snippet = rendered.lines().nth(3).unwrap();
+        let caret = rendered.lines().nth(4).unwrap();
+
+        assert_eq!(
+            snippet,
+            format!("1 | ...{}@{}...", "a".repeat(40), "b".repeat(39))
+        );
+        assert_eq!(caret.find('^'), snippet.find('@'));
+    }
+
+    #[test]
+    fn unreadable_sources_are_errors() {
+        let missing = Lexer::from_file("no/such/file.ong").map(drop).unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+
+        let invalid = Lexer::from_reader(Cursor::new(b"ok \xFF".to_vec()), 100)
+            .map(drop)
+            .unwrap_err();
+        assert_eq!(invalid.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(invalid.to_string(), "not valid UTF-8, bad byte at offset 3");
+
+        let large = Lexer::from_reader(Cursor::new(vec![b'x'; 11]), 10)
+            .map(drop)
+            .unwrap_err();
+        assert_eq!(large.kind(), io::ErrorKind::FileTooLarge);
+    }
+
+    #[test]
+    fn oversized_files_are_refused() {
+        let path = std::env::temp_dir().join(format!("orange-{}-large.ong", std::process::id()));
+        std::fs::write(&path, "let x = 1;").unwrap();
+
+        let large = Lexer::from_file_with_limit(&path, 9).map(drop).unwrap_err();
+        let lexer = Lexer::from_file_with_limit(&path, 10);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(large.kind(), io::ErrorKind::FileTooLarge);
+        assert_eq!(large.to_string(), "source is larger than the 9 byte limit");
+        assert_eq!(lexer.unwrap().source(), "let x = 1;");
+    }
+
+    #[test]
+    fn reader_sources() {
+        let mut lexer = Lexer::from_reader(Cursor::new("let x = 1;"), 100).unwrap();
+        assert!(lexer.tokenize().is_ok());
+        assert_eq!(lexer.tokens.len(), 6);
+
+        let mut empty = Lexer::from_reader(Cursor::new(""), 100).unwrap();
+        assert!(empty.tokenize().is_ok());
+        assert_eq!(empty.tokens.len(), 1);
+
+        let mut broken = Lexer::from_reader(Cursor::new("x = @;"), 100).unwrap();
+        let rendered = broken.tokenize().unwrap_err()[0].render(broken.source(), "<stdin>");
+        assert!(rendered.contains(" --> <stdin>:1:5\n"));
+    }
+
+    #[test]
+    fn eval_lines_share_one_source() {
+        let source = ["let x = 1;", "x = x + @;"].join("\n");
+        let errors = Lexer::from_source(source.as_str()).tokenize().unwrap_err();
+
+        assert!(
+            errors[0]
+                .render(&source, "<eval>")
+                .contains(" --> <eval>:2:9\n")
+        );
+    }
+
+    #[test]
+    fn doc_comments_come_before_what_they_document() {
+        let source = "## adds\n## two\n## numbers\nfn add(a, b) {}";
+        let tokens = lex(source);
+
+        let docs: Vec<&str> = tokens[..3]
+            .iter()
+            .map(|token| {
+                assert_eq!(token.token_type, TokenType::DocComment);
+                token.value(source).unwrap()
+            })
+            .collect();
+
+        assert_eq!(docs, vec!["adds", "two", "numbers"]);
+        assert_eq!(tokens[3].token_type, TokenType::Function);
+    }
+
+    #[test]
+    fn doc_comment_marker_inside_a_line_is_a_plain_comment() {
+        assert_eq!(
+            types("x = 1 ## not docs\n"),
+            vec![
+                TokenType::Identifier,
+                TokenType::Equal,
+                TokenType::Number,
+                TokenType::EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn equal_names_share_a_symbol() {
+        let mut lexer = Lexer::from_source("a b a");
+        lexer.tokenize().unwrap();
+        let symbols: Vec<Symbol> = lexer.tokens[..3]
+            .iter()
+            .map(|token| token.symbol.unwrap())
+            .collect();
+
+        assert_eq!(symbols[0], symbols[2]);
+        assert_ne!(symbols[0], symbols[1]);
+        assert_eq!(lexer.interner().resolve(symbols[1]), "b");
+        assert_eq!(lexer.interner().len(), 2);
+    }
+
+    #[test]
+    fn letters_after_a_number_are_an_error() {
+        assert_eq!(
+            error("x = 1abc;"),
+            (LexErrorKind::InvalidNumber(String::from("1abc")), 1, 5)
+        );
+        assert_eq!(error("12_;"), (LexErrorKind::MisplacedSeparator, 1, 3));
+        assert_eq!(
+            error("3.5foo"),
+            (LexErrorKind::InvalidNumber(String::from("3.5foo")), 1, 1)
+        );
+    }
+
+    #[test]
+    fn leading_byte_order_mark_is_skipped() {
+        let source = "let x = 1;\ny";
+
+        assert_eq!(positions(&format!("\u{FEFF}{}", source)), positions(source));
+        assert_eq!(types(&format!("\u{FEFF}{}", source)), types(source));
+        assert_eq!(
+            error("x \u{FEFF}"),
+            (LexErrorKind::MisplacedByteOrderMark, 1, 3)
+        );
+    }
+
+    #[test]
+    fn question_mark_forms() {
+        assert_eq!(
+            types("a ?? b ?. c ? d"),
+            vec![
+                TokenType::Identifier,
+                TokenType::QuestionQuestion,
+                TokenType::Identifier,
+                TokenType::QuestionDot,
+                TokenType::Identifier,
+                TokenType::QuestionMark,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+
+        // ?.5 is not optional chaining, and .5 on its own is rejected
+        assert_eq!(error("a ?.5"), (LexErrorKind::MissingWholePart, 1, 4));
+    }
+
+    #[test]
+    fn pipe_and_power_operators() {
+        assert_eq!(
+            types("a |> b || c ** d * e"),
+            vec![
+                TokenType::Identifier,
+                TokenType::PipeGreater,
+                TokenType::Identifier,
+                TokenType::Or,
+                TokenType::Identifier,
+                TokenType::AsteriskAsterisk,
+                TokenType::Identifier,
+                TokenType::Asterisk,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
     }
 }
@@ -1,2 +1,23 @@
+pub mod interner;
 pub mod lexer;
 pub mod parser;
+
+pub mod prelude {
+    pub use crate::interner::{Interner, Symbol};
+    pub use crate::lexer::{Lexer, Token, TokenType};
+    pub use crate::parser::{Expression, Literal, Parser, Statement};
+}
+
+/**
+ * the crate version from Cargo.toml, as printed by orange --version
+ *
+ * ```
+ * let version = orange::version();
+ *
+ * assert_eq!(version, env!("CARGO_PKG_VERSION"));
+ * assert_eq!(version.split('.').count(), 3);
+ * ```
+ */
+pub fn version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
@@ -1,6 +1,304 @@
-use orange::{lexer::Lexer, parser::Parser};
+mod cli;
+mod dump;
+
+use std::{
+    env,
+    io::{self, Read, Write},
+    mem, process,
+    time::Instant,
+};
+
+use cli::{AstFormat, DumpFormat, Options};
+use dump::Phase;
+
+use orange::{
+    lexer::{DEFAULT_MAX_SOURCE_SIZE, MAX_LEX_ERRORS},
+    prelude::*,
+};
 
 fn main() {
-    let mut _lexer = Lexer::new("samples/nocap.ong").tokenize();
-    let mut _parser = Parser::new(_lexer.tokens).parse();
+    let options = cli::parse_args(env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("orange: {}\n\n{}", error, cli::USAGE);
+        process::exit(2);
+    });
+
+    let mut out = io::stdout().lock();
+    let code = run(
+        options,
+        io::stdin().lock(),
+        &mut out,
+        &mut io::stderr().lock(),
+    )
+    .and_then(|code| out.flush().map(|_| code))
+    .unwrap_or_else(|error| {
+        eprintln!("orange: {}", error);
+        1
+    });
+
+    process::exit(code);
+}
+
+/*
+ * does what options ask for, reading the script from stdin when it is "-"
+ * returns the exit code: 0 on success, 1 when the source can't be read or has errors
+ * and 2 when there is nothing to run
+ */
+fn run(
+    options: Options,
+    stdin: impl Read,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> io::Result<i32> {
+    if options.help {
+        writeln!(out, "{}", cli::USAGE)?;
+        return Ok(0);
+    }
+
+    if options.version {
+        writeln!(out, "orange {}", orange::version())?;
+        return Ok(0);
+    }
+
+    // the name errors refer to the source by
+    let name;
+
+    let lexer = if !options.eval.is_empty() {
+        name = String::from("<eval>");
+        Ok(Lexer::from_source(options.eval.join("\n")))
+    } else {
+        let Some(script) = options.script else {
+            writeln!(err, "{}", cli::USAGE)?;
+            return Ok(2);
+        };
+
+        if script == "-" {
+            name = String::from("<stdin>");
+            Lexer::from_reader(stdin, DEFAULT_MAX_SOURCE_SIZE)
+        } else {
+            name = script;
+            Lexer::from_file(&name)
+        }
+    };
+
+    let mut lexer = match lexer {
+        Ok(lexer) => lexer,
+        Err(error) => {
+            // "No such file or directory (os error 2)" reads better without the code
+            let message = error.to_string();
+            let message = message.split(" (os error").next().unwrap_or_default();
+            writeln!(err, "orange: cannot read '{}': {}", name, message)?;
+            return Ok(1);
+        }
+    };
+
+    let mut phases = Vec::new();
+
+    let started = Instant::now();
+    if let Err(errors) = lexer.tokenize() {
+        for error in &errors {
+            writeln!(err, "{}", error.render(lexer.source(), &name))?;
+        }
+
+        if !lexer.finished() {
+            writeln!(err, "orange: stopped after {} errors", MAX_LEX_ERRORS)?;
+        }
+
+        return Ok(1);
+    }
+    phases.push(Phase {
+        name: "lexing",
+        duration: started.elapsed(),
+        counts: vec![
+            ("tokens", lexer.tokens.len()),
+            ("bytes", lexer.source().len()),
+        ],
+    });
+
+    if let Some(format) = options.tokens {
+        match format {
+            DumpFormat::Text => write!(out, "{}", dump::tokens(&lexer.tokens, lexer.source()))?,
+            DumpFormat::Json => {
+                write!(out, "{}", dump::tokens_json(&lexer.tokens, lexer.source()))?
+            }
+        }
+    } else {
+        let started = Instant::now();
+        let tokens = mem::take(&mut lexer.tokens);
+        let interner = lexer.take_interner();
+        let mut parser = Parser::new(tokens, lexer.source(), interner)
+            .with_newline_termination(options.no_semicolons);
+        let (statements, errors) = parser.parse();
+
+        if !errors.is_empty() {
+            for error in &errors {
+                writeln!(err, "{}", error.render(lexer.source(), &name))?;
+            }
+
+            // whatever did parse is still worth seeing when hunting the error
+            if let Some(format) = &options.ast {
+                write_ast(out, format, &statements, parser.interner())?;
+            }
+
+            return Ok(1);
+        }
+
+        phases.push(Phase {
+            name: "parsing",
+            duration: started.elapsed(),
+            counts: vec![
+                ("statements", statements.len()),
+                ("nodes", dump::node_count(&statements)),
+            ],
+        });
+
+        if let Some(format) = &options.ast {
+            write_ast(out, format, &statements, parser.interner())?;
+        }
+    }
+
+    match options.time {
+        Some(DumpFormat::Text) => write!(err, "{}", dump::phases(&phases))?,
+        Some(DumpFormat::Json) => write!(err, "{}", dump::phases_json(&phases))?,
+        None => {}
+    }
+
+    Ok(0)
+}
+
+fn write_ast(
+    out: &mut impl Write,
+    format: &AstFormat,
+    statements: &[Statement],
+    interner: &Interner,
+) -> io::Result<()> {
+    match format {
+        AstFormat::Debug => write!(out, "{}", dump::ast(statements, interner)),
+        AstFormat::Json => write!(out, "{}", dump::ast_json(statements, interner)),
+        AstFormat::Sexpr => write!(out, "{}", dump::ast_sexpr(statements, interner)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    // the exit code, stdout and stderr of running the arguments with stdin as input
+    fn run_with(args: &[&str], stdin: &str) -> (i32, String, String) {
+        let options = cli::parse_args(args.iter().map(|arg| arg.to_string())).unwrap();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = run(options, Cursor::new(stdin), &mut out, &mut err).unwrap();
+
+        (
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn stdin_program() {
+        let (code, out, err) = run_with(&["--ast=sexpr", "-"], "let x = 1;\nx = x + 2;");
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "(let x 1)\n(= x (+ x 2))\n");
+        assert_eq!(err, "");
+    }
+
+    #[test]
+    fn stdin_errors_cite_stdin() {
+        let (code, out, err) = run_with(&["-"], "let x = 1;\nx = @;");
+
+        assert_eq!(code, 1);
+        assert_eq!(out, "");
+        assert!(err.contains(" --> <stdin>:2:5\n"), "{}", err);
+    }
+
+    #[test]
+    fn partial_ast_is_printed_after_parse_errors() {
+        let (code, out, err) = run_with(&["--ast=sexpr", "-"], "let x = 1;\nlet y = ;\nx = 2;");
+
+        assert_eq!(code, 1);
+        assert_eq!(out, "(let x 1)\n(= x 2)\n");
+        assert!(err.contains(" --> <stdin>:2:9\n"), "{}", err);
+    }
+
+    #[test]
+    fn empty_stdin() {
+        let (code, out, err) = run_with(&["--ast=json", "-"], "");
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "[\n]\n");
+        assert_eq!(err, "");
+    }
+
+    #[test]
+    fn eval_lines_run_as_one_program() {
+        let (code, out, err) = run_with(&["-e", "let x = 1;", "-e", "x += 2;", "--ast=sexpr"], "");
+
+        assert_eq!(code, 0);
+        assert_eq!(out, "(let x 1)\n(= x (+ x 2))\n");
+        assert_eq!(err, "");
+    }
+
+    #[test]
+    fn eval_errors_cite_eval() {
+        let (code, out, err) = run_with(&["-e", "let = 1;", "--ast"], "");
+
+        assert_eq!(code, 1);
+        assert_eq!(out, "");
+        assert!(err.contains(" --> <eval>:1:5\n"), "{}", err);
+
+        let (code, _, err) = run_with(&["-e", "let x = 1;", "-e", "x = x +;"], "");
+
+        assert_eq!(code, 1);
+        assert!(err.contains(" --> <eval>:2:8\n"), "{}", err);
+    }
+
+    #[test]
+    fn phases_report_their_counts() {
+        let (code, _, err) = run_with(
+            &[
+                "--time=json",
+                "-e",
+                "let x = 1 + 2;\nwhile x > 0 { x -= 1; }",
+            ],
+            "",
+        );
+
+        assert_eq!(code, 0);
+        assert!(err.contains(", \"tokens\": 18, \"bytes\": 38}"), "{}", err);
+        assert!(
+            err.contains(", \"statements\": 2, \"nodes\": 12}"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn lexing_stops_only_after_more_than_the_cap() {
+        let (code, _, err) = run_with(&["-e", &"@".repeat(MAX_LEX_ERRORS)], "");
+
+        assert_eq!(code, 1);
+        assert!(!err.contains("stopped after"), "{}", err);
+
+        let (code, _, err) = run_with(&["-e", &"@".repeat(MAX_LEX_ERRORS + 1)], "");
+
+        assert_eq!(code, 1);
+        assert!(
+            err.ends_with("orange: stopped after 100 errors\n"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn nothing_to_run() {
+        let (code, out, err) = run_with(&[], "");
+
+        assert_eq!(code, 2);
+        assert_eq!(out, "");
+        assert_eq!(err, format!("{}\n", cli::USAGE));
+    }
 }
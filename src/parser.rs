@@ -1,13 +1,22 @@
 #![allow(dead_code)]
-use core::panic;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    mem,
+};
 
-use crate::lexer::{Token, TokenType};
+use crate::{
+    interner::{Interner, Symbol},
+    lexer::{self, Token, TokenType},
+};
 
 #[derive(Debug)]
-enum Expression {
+pub enum Expression {
     Literal(Literal),
-    Variable(String),
+    Variable(Symbol),
     Grouping(Box<Expression>),
+    // literal string parts and embedded expressions, in source order
+    Interpolation(Vec<Expression>),
 
     Unary {
         operator: TokenType,
@@ -19,36 +28,65 @@ enum Expression {
         operator: TokenType,
         rhs: Box<Expression>,
     },
+
+    Member {
+        object: Box<Expression>,
+        name: Symbol,
+        optional: bool,
+    },
+
+    // value |> function, the function is called with value as its argument
+    Pipe {
+        value: Box<Expression>,
+        function: Box<Expression>,
+    },
 }
 
 #[derive(Debug)]
-enum Literal {
+pub enum Literal {
     Number(f64),
     String(String),
+    Char(char),
     Boolean(bool),
+    Null,
 }
 
-pub struct Parser {
+pub struct Parser<'a> {
     tokens: Vec<Token>,
+    // the source the tokens were lexed from, identifiers and numbers are read from it
+    source: &'a str,
     current_index: usize,
+    // how many loop bodies enclose the current token, break and continue need at least one
+    loop_depth: usize,
+    /*
+     * the names declared in each enclosing block, innermost last, and whether they're const
+     * a name refers to its innermost declaration, reassigning it is an error if that's a const
+     */
+    scopes: Vec<HashMap<Symbol, bool>>,
+    // resolves the identifier symbols on the tokens and in the tree
+    interner: Interner,
+    // errors from statements that were skipped over, see Parser::statements
+    errors: Vec<ParseError>,
 }
 
 #[derive(Debug)]
-enum Statement {
+pub enum Statement {
     Declaration {
-        variable_name: String,
+        variable_name: Symbol,
         expression: Expression,
+        constant: bool,
     },
     Assignment {
-        variable_name: String,
+        variable_name: Symbol,
         expression: Expression,
     },
     Expression(Expression),
 
     For {
-        index: Expression,
+        variable: Symbol,
         start: Expression,
         end: Expression,
+        inclusive: bool,
         body: Vec<Statement>,
     },
 
@@ -56,43 +94,220 @@ enum Statement {
         condition: Expression,
         body: Vec<Statement>,
     },
+
+    // the body always runs once, the condition is checked after it
+    DoWhile {
+        body: Vec<Statement>,
+        condition: Expression,
+    },
+
+    Function {
+        name: Symbol,
+        params: Vec<Symbol>,
+        body: Vec<Statement>,
+    },
+
+    // else if chains nest, the else branch then holds a single If
+    If {
+        condition: Expression,
+        then_branch: Vec<Statement>,
+        else_branch: Option<Vec<Statement>>,
+    },
+
+    Break,
+    Continue,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    // expected describes what would have fit, as in "';'" or "expression"
+    Expected { expected: String, found: TokenType },
+    UnexpectedEof { expected: String },
+    OutsideLoop(TokenType),
+    ConstantReassignment(String),
+    DuplicateParameter(String),
+    InvalidNumber(String),
+    NotImplemented(TokenType),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::Expected { expected, found } => {
+                write!(f, "expected {}, got '{}'", expected, found)
+            }
+            ParseErrorKind::UnexpectedEof { expected } => {
+                write!(f, "unexpected end of input, expected {}", expected)
+            }
+            ParseErrorKind::OutsideLoop(keyword) => write!(f, "'{}' outside of a loop", keyword),
+            ParseErrorKind::ConstantReassignment(name) => {
+                write!(f, "cannot assign twice to constant '{}'", name)
+            }
+            ParseErrorKind::DuplicateParameter(name) => {
+                write!(f, "parameter '{}' is declared more than once", name)
+            }
+            ParseErrorKind::InvalidNumber(text) => write!(f, "invalid number '{}'", text),
+            ParseErrorKind::NotImplemented(keyword) => {
+                write!(f, "'{}' is reserved but not yet implemented", keyword)
+            }
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at [{}, {}]", self.kind, self.row, self.col)
+    }
+}
+
+impl ParseError {
+    // the error with the offending line of source underneath, like LexError::render
+    pub fn render(&self, source: &str, filename: &str) -> String {
+        lexer::render_error(&self.kind, self.row, self.col, source, filename)
+    }
 }
 
 /*
  * =======================GRAMMAR=============================
  *  program     ::= { statement }
  *  statement   ::= (declaration ';') | (assignment ';') | (expression ';') | loop | function
+ *                | conditional | (break ';') | (continue ';')
+ *  block       ::= '{' {statement} '}'
+ *
+ *  declaration ::= (let | const) identifier '=' expression
+ *  assignment  ::= identifier ('=' | '+=' | '-=' | '*=' | '/=' | '%=') expression
  *
- *  declaration ::= let assignment
- *  assignment  ::= identifier '=' expression
+ *  conditional ::= if expression block [else (block | conditional)]
  *
- *  loop        ::= conditioned | ranged
+ *  function    ::= fn identifier '(' [parameters] ')' block
+ *  parameters  ::= identifier {',' identifier} [',']
+ *
+ *  loop        ::= conditioned | postconditioned | ranged
  *  conditioned ::= while expression '{' {statement} '}'
+ *  postconditioned ::= do '{' {statement} '}' while expression ';'
  *  ranged      ::= for identifier in range '{' {statement} '}'
- *  range       ::= expression ',' expression
+ *  range       ::= expression ('..' | '..=' | ',') expression
  *
- *  expression  ::= logic_and {or logic_and}
+ *  expression  ::= coalesce {'|>' coalesce}
+ *  coalesce    ::= logic_or ['??' coalesce]
+ *  logic_or    ::= logic_and {or logic_and}
  *  logic_and   ::= equality {and equality}
  *  equality    ::= comparison {(bangequal | equalequal) comparison}
- *  comparison  ::= term {(greater | greaterequal | less | lessequal) term}
+ *  comparison  ::= bit_or {(greater | greaterequal | less | lessequal) bit_or}
+ *  bit_or      ::= bit_and {'|' bit_and}
+ *  bit_and     ::= shift {'&' shift}
+ *  shift       ::= term {('<<' | '>>') term}
  *  term        ::= factor {(plus | minus) factor}
  *  factor      ::= unary {(asterisk | slash | percent) unary}
- *  unary       ::= {'-' | '!'} primary
- *  primary     ::= number | identifier | string | boolean | '(' expression ')'
+ *  unary       ::= {'-' | '!' | '~'} power
+ *  power       ::= member [('^' | '**') unary]     (right-associative, -a ** b is -(a ** b))
+ *  member      ::= primary {('.' | '?.') identifier}
+ *  primary     ::= number | identifier | string | interpolation | char | boolean | null
+ *                | '(' expression ')'
+ *  interpolation ::= interpolation_start { string | '{' expression '}' } interpolation_end
+ *
+ *  number      ::= digits ['.' digits] [('e' | 'E') ['+' | '-'] digit {digit}]
+ *                | '0' ('x' | 'o' | 'b') radix_digits
+ *  digits      ::= digit {digit | '_' digit}
+ *  digit       ::= '0' | '1' | ... | '9'     (ascii only, other numerals are not digits)
+ *
+ *  identifier  ::= id_start {id_continue}
+ *  id_start    ::= '_' | alphabetic character (unicode Alphabetic property)
+ *  id_continue ::= id_start | numeric character | combining mark
  * ===========================================================
  */
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+impl<'a> Parser<'a> {
+    pub fn new(mut tokens: Vec<Token>, source: &'a str, interner: Interner) -> Self {
+        // comments only matter to tools reading the token stream
+        tokens.retain(|token| {
+            !matches!(token.token_type, TokenType::Comment | TokenType::DocComment)
+        });
+
         Parser {
-            tokens: tokens,
+            tokens,
+            source,
             current_index: 0,
+            loop_depth: 0,
+            scopes: vec![HashMap::new()],
+            interner,
+            errors: Vec::new(),
         }
     }
 
-    pub fn parse(mut self) -> Self {
-        self.program();
-        return self;
+    /*
+     * lets statements end at a line break as well as at a semicolon
+     * a semicolon is inserted at the end of a line when its last token can end a statement
+     * (a name, a literal, a closing parenthesis or bracket, break or continue),
+     * so a line ending in an operator or an open parenthesis carries on to the next one
+     * a line starting with an opening brace carries on the previous one as well,
+     * since a brace never starts a statement, so a body may open on its own line
+     * nothing is inserted inside parentheses, brackets or string interpolations,
+     * and the same applies before a closing brace and at the end of the source
+     */
+    pub fn with_newline_termination(mut self, enabled: bool) -> Self {
+        if !enabled {
+            return self;
+        }
+
+        let mut tokens: Vec<Token> = Vec::with_capacity(self.tokens.len());
+        let mut depth = 0usize;
+
+        for token in mem::take(&mut self.tokens) {
+            if let Some(previous) = tokens.last() {
+                let at_line_break = self.source[previous.end..token.start].contains('\n')
+                    || matches!(token.token_type, TokenType::RightBrace | TokenType::EOF);
+
+                let opens_block = token.token_type == TokenType::LeftBrace;
+
+                if depth == 0 && at_line_break && !opens_block && ends_statement(previous) {
+                    let terminator = Token::new(
+                        TokenType::Semicolon,
+                        None,
+                        (previous.row, previous.col),
+                        (previous.end, previous.end),
+                    );
+                    tokens.push(terminator);
+                }
+            }
+
+            match token.token_type {
+                TokenType::LeftParenthesis
+                | TokenType::LeftBracket
+                | TokenType::InterpolationStart => depth += 1,
+                TokenType::RightParenthesis
+                | TokenType::RightBracket
+                | TokenType::InterpolationEnd => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+
+            tokens.push(token);
+        }
+
+        self.tokens = tokens;
+        self
+    }
+
+    /*
+     * parses the whole token stream into its top-level statements
+     * a statement with a syntax error is left out and parsing carries on after it,
+     * so the statements come back along with every error found, in source order
+     */
+    pub fn parse(&mut self) -> (Vec<Statement>, Vec<ParseError>) {
+        let statements = self.program();
+        (statements, mem::take(&mut self.errors))
+    }
+
+    // resolves the symbols in the returned statements back to their names
+    pub fn interner(&self) -> &Interner {
+        &self.interner
     }
 
     fn current(&self) -> &Token {
@@ -105,159 +320,541 @@ impl Parser {
 
     /*
      * compares the current token to the supplied token_type
-     * advances if they match and panics if they don't
+     * advances if they match and fails if they don't
      */
-    fn expect(&mut self, token_type: TokenType) {
+    fn expect(&mut self, token_type: TokenType) -> Result<(), ParseError> {
         if self.current().token_type == token_type {
             self.advance();
+            Ok(())
         } else {
-            panic!(
-                "error: parser; expected '{}', got '{}'",
-                token_type,
-                self.current().token_type
-            );
+            Err(self.unexpected(format!("'{}'", token_type)))
         }
     }
 
-    // advances to the next token
+    // an error at the current token, which isn't the expected one
+    fn unexpected(&self, expected: impl Into<String>) -> ParseError {
+        let expected = expected.into();
+        let found = self.current().token_type.clone();
+
+        let kind = match found {
+            TokenType::EOF => ParseErrorKind::UnexpectedEof { expected },
+            found => ParseErrorKind::Expected { expected, found },
+        };
+
+        self.error(kind)
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            row: self.current().row,
+            col: self.current().col,
+        }
+    }
+
+    // keywords set aside for syntax that doesn't exist yet
+    fn reserved_keyword(&self) -> ParseError {
+        self.error(ParseErrorKind::NotImplemented(
+            self.current().token_type.clone(),
+        ))
+    }
+
+    /*
+     * the value of the current token as an owned string
+     * a value the token already owns is moved out rather than cloned
+     */
+    fn take_value(&mut self) -> String {
+        let token = &mut self.tokens[self.current_index];
+
+        match token.token_name.take() {
+            Some(value) => value,
+            None => token.lexeme(self.source).to_string(),
+        }
+    }
+
+    // the interned name of the current token, which must be an identifier
+    fn symbol(&self) -> Result<Symbol, ParseError> {
+        self.current()
+            .symbol
+            .ok_or_else(|| self.unexpected("identifier"))
+    }
+
+    // advances to the next token, staying put on the final EOF
     fn advance(&mut self) {
         if self.peek().is_some() {
             self.current_index += 1;
-        } else {
-            eprintln!("reached the end of tokens vector")
         }
     }
 
-    fn program(&mut self) {
+    fn program(&mut self) -> Vec<Statement> {
+        self.statements(TokenType::EOF)
+    }
+
+    /*
+     * statements up to (not including) the end token or the end of the source
+     * a statement that fails is recorded in self.errors and skipped with synchronize
+     */
+    fn statements(&mut self, end: TokenType) -> Vec<Statement> {
         let mut stmts = Vec::<Statement>::new();
 
-        while !matches!(self.current().token_type, TokenType::EOF) {
-            stmts.push(self.statement());
+        while self.current().token_type != end && self.current().token_type != TokenType::EOF {
+            let start = self.current_index;
+
+            match self.statement() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(error) => {
+                    self.errors.push(error);
+
+                    // a token no statement can start with would be hit again and again
+                    if self.current_index == start {
+                        self.advance();
+                    }
+
+                    self.synchronize();
+                }
+            }
         }
 
-        dbg!(stmts);
+        stmts
     }
 
-    fn _x(&mut self) -> Vec<Box<Statement>> {
+    /*
+     * skips to where the next statement most likely starts: past a ';',
+     * or up to a '}' closing the enclosing block or a keyword that begins a statement
+     * a block opened along the way is skipped whole, so a loop whose header failed
+     * doesn't leave its body behind to be parsed as statements of its own
+     */
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+
+        loop {
+            match self.current().token_type {
+                TokenType::EOF => return,
+
+                TokenType::LeftBrace => {
+                    depth += 1;
+                    self.advance();
+                }
+
+                TokenType::RightBrace if depth > 0 => {
+                    depth -= 1;
+                    self.advance();
+
+                    if depth == 0 {
+                        return;
+                    }
+                }
+
+                _ if depth > 0 => self.advance(),
+
+                TokenType::Semicolon => {
+                    self.advance();
+                    return;
+                }
+
+                TokenType::RightBrace
+                | TokenType::Let
+                | TokenType::Const
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Do
+                | TokenType::For
+                | TokenType::Function
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue => return,
+
+                _ => self.advance(),
+            }
+        }
+    }
+
+    fn _x(&mut self) -> Vec<Statement> {
         Vec::default()
     }
 
-    fn statement(&mut self) -> Statement {
+    fn statement(&mut self) -> Result<Statement, ParseError> {
         match self.current().token_type.clone() {
-            TokenType::Let => {
-                let stmt = self.declaration();
-                self.expect(TokenType::Semicolon);
-                stmt
+            TokenType::Let | TokenType::Const => {
+                let stmt = self.declaration()?;
+                self.expect(TokenType::Semicolon)?;
+                Ok(stmt)
             }
 
-            TokenType::Identifier => {
-                let expr = self.assignment();
-                self.expect(TokenType::Semicolon);
-                Statement::Assignment {
-                    variable_name: self.current().token_name.clone().unwrap(),
-                    expression: expr,
-                }
+            // a name starts an assignment only when = or op= follows it, as in x |> f;
+            TokenType::Identifier
+                if self.peek().is_some_and(|next| {
+                    matches!(
+                        next.token_type,
+                        TokenType::Equal
+                            | TokenType::PlusEqual
+                            | TokenType::MinusEqual
+                            | TokenType::AsteriskEqual
+                            | TokenType::SlashEqual
+                            | TokenType::PercentEqual
+                    )
+                }) =>
+            {
+                let stmt = self.reassignment()?;
+                self.expect(TokenType::Semicolon)?;
+                Ok(stmt)
             }
 
-            TokenType::For => {
-                let stmt = self.ranged();
-                self.expect(TokenType::RightBrace);
+            TokenType::For => self.ranged(),
 
-                stmt
-            }
+            TokenType::Break | TokenType::Continue => {
+                let keyword = self.current().token_type.clone();
+
+                if self.loop_depth == 0 {
+                    return Err(self.error(ParseErrorKind::OutsideLoop(keyword)));
+                }
 
-            TokenType::While => {
                 self.advance();
-                self.expression();
+                self.expect(TokenType::Semicolon)?;
 
-                panic!();
+                match keyword {
+                    TokenType::Break => Ok(Statement::Break),
+                    _ => Ok(Statement::Continue),
+                }
+            }
+
+            TokenType::If => self.conditional(),
+
+            TokenType::Function => self.function(),
+
+            TokenType::While => self.conditioned(),
+
+            TokenType::Do => {
+                let stmt = self.postconditioned()?;
+                self.expect(TokenType::Semicolon)?;
+                Ok(stmt)
             }
 
             _ => {
-                let expression = self.expression();
-                self.expect(TokenType::Semicolon);
-                Statement::Expression(expression)
+                let expression = self.expression()?;
+                self.expect(TokenType::Semicolon)?;
+                Ok(Statement::Expression(expression))
             }
         }
     }
 
-    fn ranged(&mut self) -> Statement {
-        let mut stmts: Vec<Statement> = Vec::new();
-        // for ident in expr, expr {}
+    // while cond { ... }, the condition is checked before every run of the body
+    fn conditioned(&mut self) -> Result<Statement, ParseError> {
         self.advance();
-        let ident = self.expression();
-        self.expect(TokenType::In);
-        let start = self.expression();
-        self.expect(TokenType::Comma);
-        let end = self.expression();
-        self.expect(TokenType::LeftBrace);
+        let condition = self.expression()?;
 
-        while self.current().token_type != TokenType::RightBrace {
-            stmts.push(self.statement());
-        }
+        self.loop_depth += 1;
+        let body = self.block();
+        self.loop_depth -= 1;
+
+        Ok(Statement::While {
+            condition,
+            body: body?,
+        })
+    }
+
+    // do { ... } while cond, the condition is checked after every run of the body
+    fn postconditioned(&mut self) -> Result<Statement, ParseError> {
+        self.advance();
+
+        self.loop_depth += 1;
+        let body = self.block();
+        self.loop_depth -= 1;
+        let body = body?;
+
+        self.expect(TokenType::While)?;
+        let condition = self.expression()?;
 
-        Statement::For {
-            index: ident,
+        Ok(Statement::DoWhile { body, condition })
+    }
+
+    fn ranged(&mut self) -> Result<Statement, ParseError> {
+        // for ident in expr..expr {}, with ..= including the end and ',' meaning ..
+        self.advance();
+        let variable = self.symbol()?;
+        self.advance();
+        self.expect(TokenType::In)?;
+        let start = self.expression()?;
+
+        let inclusive = match self.current().token_type {
+            TokenType::DotDotEqual => true,
+            TokenType::DotDot | TokenType::Comma => false,
+            _ => return Err(self.unexpected("'..', '..=' or ','")),
+        };
+
+        self.advance();
+        let end = self.expression()?;
+
+        self.loop_depth += 1;
+        let body = self.block_binding(&[variable]);
+        self.loop_depth -= 1;
+
+        Ok(Statement::For {
+            variable,
             start,
             end,
-            body: stmts,
+            inclusive,
+            body: body?,
+        })
+    }
+
+    // if cond { ... } else { ... }, where an else may be followed by another if instead
+    fn conditional(&mut self) -> Result<Statement, ParseError> {
+        self.advance();
+        let condition = self.expression()?;
+        let then_branch = self.block()?;
+
+        let else_branch = if self.current().token_type == TokenType::Else {
+            self.advance();
+
+            if self.current().token_type == TokenType::If {
+                Some(vec![self.conditional()?])
+            } else {
+                Some(self.block()?)
+            }
+        } else {
+            None
+        };
+
+        Ok(Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    /*
+     * fn name(a, b) { ... }, the parameter list may end with a trailing comma
+     * the body is a fresh context: a break in it doesn't reach a loop around the declaration
+     */
+    fn function(&mut self) -> Result<Statement, ParseError> {
+        self.advance();
+        let name = self.symbol()?;
+        self.advance();
+        self.expect(TokenType::LeftParenthesis)?;
+
+        let mut params = Vec::new();
+
+        while self.current().token_type != TokenType::RightParenthesis {
+            let param = self.symbol()?;
+
+            if params.contains(&param) {
+                let name = self.interner.resolve(param).to_string();
+                return Err(self.error(ParseErrorKind::DuplicateParameter(name)));
+            }
+
+            params.push(param);
+            self.advance();
+
+            if self.current().token_type != TokenType::Comma {
+                break;
+            }
+
+            self.advance();
         }
+
+        self.expect(TokenType::RightParenthesis)?;
+
+        let loop_depth = mem::take(&mut self.loop_depth);
+        let body = self.block_binding(&params);
+        self.loop_depth = loop_depth;
+
+        Ok(Statement::Function {
+            name,
+            params,
+            body: body?,
+        })
+    }
+
+    // a braced list of statements, with a scope of its own for the names declared in it
+    fn block(&mut self) -> Result<Vec<Statement>, ParseError> {
+        self.expect(TokenType::LeftBrace)?;
+        self.scopes.push(HashMap::new());
+        let stmts = self.statements(TokenType::RightBrace);
+        self.scopes.pop();
+        self.expect(TokenType::RightBrace)?;
+
+        Ok(stmts)
+    }
+
+    /*
+     * a block with some names already declared in a scope around it,
+     * the variable of a for loop or the parameters of a function
+     */
+    fn block_binding(&mut self, names: &[Symbol]) -> Result<Vec<Statement>, ParseError> {
+        self.scopes
+            .push(names.iter().map(|&name| (name, false)).collect());
+        let block = self.block();
+        self.scopes.pop();
+
+        block
+    }
+
+    fn declare(&mut self, name: Symbol, constant: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, constant);
+        }
+    }
+
+    // whether the innermost declaration of name is a const, names never declared aren't
+    fn is_constant(&self, name: Symbol) -> bool {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name))
+            .copied()
+            .unwrap_or(false)
     }
 
-    fn declaration(&mut self) -> Statement {
+    fn declaration(&mut self) -> Result<Statement, ParseError> {
+        let constant = self.current().token_type == TokenType::Const;
         self.advance();
-        let name = self.current().token_name.clone().unwrap();
-        let value = self.assignment();
-        Statement::Declaration {
+
+        if self.current().token_type == TokenType::Match {
+            return Err(self.reserved_keyword());
+        }
+
+        let name = self.symbol()?;
+        let value = self.assignment()?;
+
+        // a later let with the same name in the same block shadows the constant
+        self.declare(name, constant);
+
+        Ok(Statement::Declaration {
             variable_name: name,
             expression: value,
-        }
+            constant,
+        })
     }
 
-    fn assignment(&mut self) -> Expression {
-        self.expect(TokenType::Identifier);
-        self.expect(TokenType::Equal);
+    fn assignment(&mut self) -> Result<Expression, ParseError> {
+        self.expect(TokenType::Identifier)?;
+        self.expect(TokenType::Equal)?;
         self.expression()
     }
 
-    fn expression(&mut self) -> Expression {
-        let mut expression = self.logic_and();
+    /*
+     * assignment to an existing variable
+     * compound forms like `x += e` desugar into `x = x + e`
+     */
+    fn reassignment(&mut self) -> Result<Statement, ParseError> {
+        let variable_name = self.symbol()?;
+
+        if self.is_constant(variable_name) {
+            let name = self.interner.resolve(variable_name).to_string();
+            return Err(self.error(ParseErrorKind::ConstantReassignment(name)));
+        }
+
+        self.expect(TokenType::Identifier)?;
+
+        let operator = match self.current().token_type {
+            TokenType::PlusEqual => Some(TokenType::Plus),
+            TokenType::MinusEqual => Some(TokenType::Minus),
+            TokenType::AsteriskEqual => Some(TokenType::Asterisk),
+            TokenType::SlashEqual => Some(TokenType::Slash),
+            TokenType::PercentEqual => Some(TokenType::Percent),
+            _ => None,
+        };
+
+        let expression = match operator {
+            Some(operator) => {
+                self.advance();
+                Expression::Binary {
+                    lhs: Box::new(Expression::Variable(variable_name)),
+                    operator,
+                    rhs: Box::new(self.expression()?),
+                }
+            }
+
+            None => {
+                self.expect(TokenType::Equal)?;
+                self.expression()?
+            }
+        };
+
+        Ok(Statement::Assignment {
+            variable_name,
+            expression,
+        })
+    }
+
+    // value |> f |> g feeds each value to the next function, left to right
+    fn expression(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.coalesce()?;
+
+        while self.current().token_type == TokenType::PipeGreater {
+            self.advance();
+            let function = self.coalesce()?;
+
+            expression = Expression::Pipe {
+                value: Box::new(expression),
+                function: Box::new(function),
+            }
+        }
+
+        Ok(expression)
+    }
+
+    /*
+     * a ?? b is a unless a is null, else b
+     * it groups to the right, a ?? b ?? c being a ?? (b ?? c), and short-circuits:
+     * b is only evaluated when a turns out to be null
+     */
+    fn coalesce(&mut self) -> Result<Expression, ParseError> {
+        let expression = self.logic_or()?;
+
+        if self.current().token_type != TokenType::QuestionQuestion {
+            return Ok(expression);
+        }
+
+        let operator = self.current().token_type.clone();
+        self.advance();
+
+        Ok(Expression::Binary {
+            lhs: Box::new(expression),
+            operator,
+            rhs: Box::new(self.coalesce()?),
+        })
+    }
+
+    fn logic_or(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.logic_and()?;
 
         while matches!(self.current().token_type, TokenType::Or) {
             let operator = self.current().token_type.clone();
             self.advance();
-            let rhs = self.logic_and();
+            let rhs = self.logic_and()?;
 
             expression = Expression::Binary {
                 lhs: Box::new(expression),
-                operator: operator,
+                operator,
                 rhs: Box::new(rhs),
             }
         }
 
-        expression
+        Ok(expression)
     }
 
-    fn logic_and(&mut self) -> Expression {
-        let mut expression = self.equality();
+    fn logic_and(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.equality()?;
 
         while matches!(self.current().token_type, TokenType::And) {
             let operator = self.current().token_type.clone();
             self.advance();
-            let rhs = self.equality();
+            let rhs = self.equality()?;
 
             expression = Expression::Binary {
                 lhs: Box::new(expression),
-                operator: operator,
+                operator,
                 rhs: Box::new(rhs),
             }
         }
 
-        expression
+        Ok(expression)
     }
 
-    fn equality(&mut self) -> Expression {
-        let mut expression = self.comparison();
+    fn equality(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.comparison()?;
 
         while matches!(
             self.current().token_type,
@@ -265,20 +862,20 @@ impl Parser {
         ) {
             let operator = self.current().token_type.clone();
             self.advance();
-            let rhs = self.comparison();
+            let rhs = self.comparison()?;
 
             expression = Expression::Binary {
                 lhs: Box::new(expression),
-                operator: operator,
+                operator,
                 rhs: Box::new(rhs),
             }
         }
 
-        expression
+        Ok(expression)
     }
 
-    fn comparison(&mut self) -> Expression {
-        let mut expression = self.term();
+    fn comparison(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.bit_or()?;
 
         while matches!(
             self.current().token_type,
@@ -286,20 +883,77 @@ impl Parser {
         ) {
             let operator = self.current().token_type.clone();
             self.advance();
-            let rhs = self.term();
+            let rhs = self.bit_or()?;
+
+            expression = Expression::Binary {
+                lhs: Box::new(expression),
+                operator,
+                rhs: Box::new(rhs),
+            }
+        }
+
+        Ok(expression)
+    }
+
+    fn bit_or(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.bit_and()?;
+
+        while matches!(self.current().token_type, TokenType::BitOr) {
+            let operator = self.current().token_type.clone();
+            self.advance();
+            let rhs = self.bit_and()?;
 
             expression = Expression::Binary {
                 lhs: Box::new(expression),
-                operator: operator,
+                operator,
                 rhs: Box::new(rhs),
             }
         }
 
-        expression
+        Ok(expression)
     }
 
-    fn term(&mut self) -> Expression {
-        let mut expression = self.factor();
+    fn bit_and(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.shift()?;
+
+        while matches!(self.current().token_type, TokenType::BitAnd) {
+            let operator = self.current().token_type.clone();
+            self.advance();
+            let rhs = self.shift()?;
+
+            expression = Expression::Binary {
+                lhs: Box::new(expression),
+                operator,
+                rhs: Box::new(rhs),
+            }
+        }
+
+        Ok(expression)
+    }
+
+    fn shift(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.term()?;
+
+        while matches!(
+            self.current().token_type,
+            TokenType::ShiftLeft | TokenType::ShiftRight
+        ) {
+            let operator = self.current().token_type.clone();
+            self.advance();
+            let rhs = self.term()?;
+
+            expression = Expression::Binary {
+                lhs: Box::new(expression),
+                operator,
+                rhs: Box::new(rhs),
+            }
+        }
+
+        Ok(expression)
+    }
+
+    fn term(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.factor()?;
 
         while matches!(
             self.current().token_type,
@@ -307,20 +961,20 @@ impl Parser {
         ) {
             let operator = self.current().token_type.clone();
             self.advance();
-            let rhs = self.factor();
+            let rhs = self.factor()?;
 
             expression = Expression::Binary {
                 lhs: Box::new(expression),
-                operator: operator,
+                operator,
                 rhs: Box::new(rhs),
             }
         }
 
-        expression
+        Ok(expression)
     }
 
-    fn factor(&mut self) -> Expression {
-        let mut expression = self.unary();
+    fn factor(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.unary()?;
 
         while matches!(
             self.current().token_type,
@@ -328,67 +982,953 @@ impl Parser {
         ) {
             let operator = self.current().token_type.clone();
             self.advance();
-            let rhs = self.unary();
+            let rhs = self.unary()?;
 
             expression = Expression::Binary {
                 lhs: Box::new(expression),
-                operator: operator,
+                operator,
                 rhs: Box::new(rhs),
             }
         }
 
-        expression
+        Ok(expression)
     }
 
-    fn unary(&mut self) -> Expression {
+    fn unary(&mut self) -> Result<Expression, ParseError> {
         if matches!(
             self.current().token_type,
-            TokenType::Minus | TokenType::Bang
+            TokenType::Minus | TokenType::Bang | TokenType::BitNot
         ) {
             let operator = self.current().token_type.clone();
             self.advance();
-            let rhs = self.unary();
+            let rhs = self.unary()?;
 
-            Expression::Unary {
-                operator: operator,
+            Ok(Expression::Unary {
+                operator,
                 rhs: Box::new(rhs),
-            }
+            })
         } else {
-            self.primary()
+            self.power()
+        }
+    }
+
+    /*
+     * a ^ b and a ** b both raise a to the power b
+     * they group to the right, 2 ** 3 ** 2 being 2 ** (3 ** 2), and bind tighter than
+     * a unary operator on their left, so -a ** b is -(a ** b), while a ** -b still works
+     */
+    fn power(&mut self) -> Result<Expression, ParseError> {
+        let expression = self.member()?;
+
+        if !matches!(
+            self.current().token_type,
+            TokenType::Caret | TokenType::AsteriskAsterisk
+        ) {
+            return Ok(expression);
         }
+
+        let operator = self.current().token_type.clone();
+        self.advance();
+
+        Ok(Expression::Binary {
+            lhs: Box::new(expression),
+            operator,
+            rhs: Box::new(self.unary()?),
+        })
+    }
+
+    // a.b reads a member, a?.b does the same but gives null rather than failing when a is null
+    fn member(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.primary()?;
+
+        while matches!(
+            self.current().token_type,
+            TokenType::Dot | TokenType::QuestionDot
+        ) {
+            let optional = self.current().token_type == TokenType::QuestionDot;
+            self.advance();
+            let name = self.symbol()?;
+            self.advance();
+
+            expression = Expression::Member {
+                object: Box::new(expression),
+                name,
+                optional,
+            }
+        }
+
+        Ok(expression)
     }
 
-    fn primary(&mut self) -> Expression {
+    fn interpolation(&mut self) -> Result<Expression, ParseError> {
+        let mut parts = Vec::new();
+        self.advance();
+
+        loop {
+            match self.current().token_type {
+                TokenType::String => {
+                    let value = self.take_value();
+                    self.advance();
+                    parts.push(Expression::Literal(Literal::String(value)));
+                }
+
+                TokenType::LeftBrace => {
+                    self.advance();
+                    parts.push(self.expression()?);
+                    self.expect(TokenType::RightBrace)?;
+                }
+
+                _ => break,
+            }
+        }
+
+        self.expect(TokenType::InterpolationEnd)?;
+        Ok(Expression::Interpolation(parts))
+    }
+
+    fn primary(&mut self) -> Result<Expression, ParseError> {
         match self.current().token_type {
             TokenType::Number => {
-                let value = self
-                    .current()
-                    .token_name
-                    .clone()
-                    .unwrap()
-                    .parse::<f64>()
-                    .unwrap();
+                let text = self.current().value(self.source).unwrap_or_default();
+
+                let Ok(value) = text.parse::<f64>() else {
+                    let kind = ParseErrorKind::InvalidNumber(text.to_string());
+                    return Err(self.error(kind));
+                };
+
                 self.advance();
-                Expression::Literal(Literal::Number(value))
+                Ok(Expression::Literal(Literal::Number(value)))
             }
 
             TokenType::Identifier => {
-                let name = self.current().token_name.clone().unwrap();
+                let name = self.symbol()?;
                 self.advance();
-                Expression::Variable(name)
+                Ok(Expression::Variable(name))
+            }
+
+            TokenType::Boolean => {
+                let value = self.current().lexeme(self.source) == "true";
+                self.advance();
+                Ok(Expression::Literal(Literal::Boolean(value)))
+            }
+
+            TokenType::Null => {
+                self.advance();
+                Ok(Expression::Literal(Literal::Null))
+            }
+
+            TokenType::String => {
+                let value = self.take_value();
+                self.advance();
+                Ok(Expression::Literal(Literal::String(value)))
+            }
+
+            TokenType::InterpolationStart => self.interpolation(),
+
+            TokenType::Char => {
+                let value = self.take_value();
+                self.advance();
+                Ok(Expression::Literal(Literal::Char(
+                    value.chars().next().unwrap(),
+                )))
             }
 
             TokenType::LeftParenthesis => {
                 self.advance();
-                let expression = self.expression();
-                self.expect(TokenType::RightParenthesis);
-                Expression::Grouping(Box::new(expression))
+                let expression = self.expression()?;
+                self.expect(TokenType::RightParenthesis)?;
+                Ok(Expression::Grouping(Box::new(expression)))
             }
 
-            _ => panic!(
-                "error: parser; expected expression, got {:?}",
-                self.current().token_type
+            TokenType::Match => Err(self.reserved_keyword()),
+
+            _ => Err(self.unexpected("expression")),
+        }
+    }
+}
+
+// whether a statement may end with this token, see Parser::with_newline_termination
+fn ends_statement(token: &Token) -> bool {
+    matches!(
+        token.token_type,
+        TokenType::Identifier
+            | TokenType::Number
+            | TokenType::String
+            | TokenType::Char
+            | TokenType::Boolean
+            | TokenType::Null
+            | TokenType::InterpolationEnd
+            | TokenType::RightParenthesis
+            | TokenType::RightBracket
+            | TokenType::Break
+            | TokenType::Continue
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn parse_with(source: &str, newlines: bool) -> (Vec<Statement>, Vec<ParseError>, Interner) {
+        let mut lexer = Lexer::from_source(source);
+        lexer.tokenize().unwrap();
+
+        let tokens = mem::take(&mut lexer.tokens);
+        let interner = lexer.take_interner();
+        let mut parser =
+            Parser::new(tokens, lexer.source(), interner).with_newline_termination(newlines);
+        let (statements, errors) = parser.parse();
+
+        (statements, errors, parser.interner)
+    }
+
+    fn parse(source: &str) -> (Vec<Statement>, Interner) {
+        let (statements, errors, interner) = parse_with(source, false);
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+
+        (statements, interner)
+    }
+
+    fn errors(source: &str) -> Vec<(ParseErrorKind, usize, usize)> {
+        parse_with(source, false)
+            .1
+            .into_iter()
+            .map(|error| (error.kind, error.row, error.col))
+            .collect()
+    }
+
+    fn expected(expected: &str, found: TokenType) -> ParseErrorKind {
+        ParseErrorKind::Expected {
+            expected: expected.to_string(),
+            found,
+        }
+    }
+
+    // the expression as an s-expression, operators first: (+ a (* b 2))
+    fn show(expression: &Expression, interner: &Interner) -> String {
+        match expression {
+            Expression::Literal(Literal::Number(value)) => value.to_string(),
+            Expression::Literal(Literal::String(value)) => format!("{:?}", value),
+            Expression::Literal(Literal::Char(value)) => format!("{:?}", value),
+            Expression::Literal(Literal::Boolean(value)) => value.to_string(),
+            Expression::Literal(Literal::Null) => String::from("null"),
+            Expression::Variable(name) => interner.resolve(*name).to_string(),
+            Expression::Grouping(inner) => format!("(group {})", show(inner, interner)),
+            Expression::Interpolation(parts) => {
+                let parts: Vec<String> = parts.iter().map(|part| show(part, interner)).collect();
+                format!("(interpolation {})", parts.join(" "))
+            }
+            Expression::Unary { operator, rhs } => {
+                format!("({} {})", operator, show(rhs, interner))
+            }
+            Expression::Binary { lhs, operator, rhs } => format!(
+                "({} {} {})",
+                operator,
+                show(lhs, interner),
+                show(rhs, interner)
+            ),
+            Expression::Member {
+                object,
+                name,
+                optional,
+            } => format!(
+                "({} {} {})",
+                if *optional { "?." } else { "." },
+                show(object, interner),
+                interner.resolve(*name)
+            ),
+            Expression::Pipe { value, function } => format!(
+                "(|> {} {})",
+                show(value, interner),
+                show(function, interner)
             ),
         }
     }
+
+    // the value of `let e = <source>;`, shown as an s-expression
+    fn expression(source: &str) -> String {
+        let (statements, interner) = parse(&format!("let e = {};", source));
+
+        match &statements[..] {
+            [Statement::Declaration { expression, .. }] => show(expression, &interner),
+            other => panic!("expected a single declaration, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_returns_the_statements() {
+        let (statements, interner) = parse("let x = 1 + 2; const y = x; x = 3;");
+
+        assert_eq!(statements.len(), 3);
+
+        let Statement::Declaration {
+            variable_name,
+            expression,
+            constant: false,
+        } = &statements[0]
+        else {
+            panic!("expected a let, got {:?}", statements[0]);
+        };
+        assert_eq!(interner.resolve(*variable_name), "x");
+        assert_eq!(show(expression, &interner), "(+ 1 2)");
+
+        assert!(matches!(
+            statements[1],
+            Statement::Declaration { constant: true, .. }
+        ));
+
+        let Statement::Assignment {
+            variable_name,
+            expression,
+        } = &statements[2]
+        else {
+            panic!("expected an assignment, got {:?}", statements[2]);
+        };
+        assert_eq!(interner.resolve(*variable_name), "x");
+        assert_eq!(show(expression, &interner), "3");
+    }
+
+    #[test]
+    fn precedence_follows_the_grammar() {
+        assert_eq!(
+            expression("a || b && c == d < e | f & g << h + i * -j ** k"),
+            "(|| a (&& b (== c (< d (| e (& f (<< g (+ h (* i (- (** j k)))))))))))"
+        );
+        assert_eq!(expression("(a + b) * c"), "(* (group (+ a b)) c)");
+    }
+
+    #[test]
+    fn missing_semicolon() {
+        assert_eq!(
+            errors("let x = 1\nlet y = 2;"),
+            vec![(expected("';'", TokenType::Let), 2, 1)]
+        );
+        assert_eq!(
+            errors("let x = 1"),
+            vec![(
+                ParseErrorKind::UnexpectedEof {
+                    expected: String::from("';'")
+                },
+                1,
+                10
+            )]
+        );
+    }
+
+    #[test]
+    fn unbalanced_parentheses() {
+        assert_eq!(
+            errors("let x = (1 + 2;"),
+            vec![(expected("')'", TokenType::Semicolon), 1, 15)]
+        );
+        assert_eq!(
+            errors("let x = 1 + 2);"),
+            vec![(expected("';'", TokenType::RightParenthesis), 1, 14)]
+        );
+    }
+
+    #[test]
+    fn stray_token() {
+        assert_eq!(
+            errors("let x = 1; ) let y = 2;"),
+            vec![(expected("expression", TokenType::RightParenthesis), 1, 12)]
+        );
+        assert_eq!(
+            errors("let 3 = 1;"),
+            vec![(expected("identifier", TokenType::Number), 1, 5)]
+        );
+    }
+
+    #[test]
+    fn errors_display_their_position() {
+        let (_, errors, _) = parse_with("let x = ;", false);
+
+        assert_eq!(
+            errors[0].to_string(),
+            "expected expression, got ';' at [1, 9]"
+        );
+    }
+
+    #[test]
+    fn parsing_carries_on_after_an_error() {
+        let source = "let a = 1;\nlet b = (2 + ;\nlet c = 3;\nlet d = * 4;\nlet e = 5;\nlet = 6;\nlet f = 7;";
+        let (statements, errors, interner) = parse_with(source, false);
+
+        let rows: Vec<usize> = errors.iter().map(|error| error.row).collect();
+        assert_eq!(rows, vec![2, 4, 6]);
+
+        let names: Vec<&str> = statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::Declaration { variable_name, .. } => interner.resolve(*variable_name),
+                other => panic!("expected a declaration, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(names, vec!["a", "c", "e", "f"]);
+    }
+
+    #[test]
+    fn recovery_inside_a_block_keeps_the_block() {
+        let (statements, errors, _) = parse_with("while x { let a = ; y = 1; }\nlet b = 2;", false);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(statements.len(), 2);
+
+        let Statement::While { body, .. } = &statements[0] else {
+            panic!("expected a while, got {:?}", statements[0]);
+        };
+        assert_eq!(body.len(), 1);
+    }
+
+    #[test]
+    fn number_literals() {
+        assert_eq!(expression("0x1F + 0b101 + 0o17"), "(+ (+ 31 5) 15)");
+        assert_eq!(expression("1e3 - 2.5e-1"), "(- 1000 0.25)");
+        assert_eq!(expression("1_0.5_5"), "10.55");
+    }
+
+    #[test]
+    fn number_tokens_that_are_not_numbers_are_errors() {
+        let tokens = vec![
+            Token::new(TokenType::Let, None, (1, 1), (0, 3)),
+            Token::new(TokenType::Identifier, None, (1, 5), (4, 5)),
+            Token::new(TokenType::Equal, None, (1, 7), (6, 7)),
+            Token::new(TokenType::Number, None, (1, 9), (8, 10)),
+            Token::new(TokenType::Semicolon, None, (1, 10), (10, 11)),
+            Token::new(TokenType::EOF, None, (1, 11), (11, 11)),
+        ];
+        let mut interner = Interner::new();
+        let mut tokens = tokens;
+        tokens[1].symbol = Some(interner.intern("x"));
+
+        let (_, errors) = Parser::new(tokens, "let x = ½;", interner).parse();
+
+        assert_eq!(
+            errors,
+            vec![ParseError {
+                kind: ParseErrorKind::InvalidNumber(String::from("½")),
+                row: 1,
+                col: 9
+            }]
+        );
+    }
+
+    #[test]
+    fn compound_assignment_desugars() {
+        for operator in ["+", "-", "*", "/", "%"] {
+            let (compound, interner) = parse(&format!("x {}= y + 1;", operator));
+            let (expanded, _) = parse(&format!("x = x {} (y + 1);", operator));
+
+            let (
+                [
+                    Statement::Assignment {
+                        expression: compound,
+                        ..
+                    },
+                ],
+                [
+                    Statement::Assignment {
+                        expression: expanded,
+                        ..
+                    },
+                ],
+            ) = (&compound[..], &expanded[..])
+            else {
+                panic!("expected assignments");
+            };
+
+            assert_eq!(
+                show(compound, &interner),
+                show(expanded, &interner).replace("(group (+ y 1))", "(+ y 1)")
+            );
+        }
+    }
+
+    #[test]
+    fn ranged_for_loops() {
+        let (statements, interner) = parse("for i in 0..10 {} for j in 0..=10 {}");
+
+        let [
+            Statement::For {
+                variable,
+                start,
+                end,
+                inclusive: false,
+                body,
+            },
+            Statement::For {
+                inclusive: true, ..
+            },
+        ] = &statements[..]
+        else {
+            panic!("expected two for loops, got {:?}", statements);
+        };
+
+        assert_eq!(interner.resolve(*variable), "i");
+        assert_eq!(show(start, &interner), "0");
+        assert_eq!(show(end, &interner), "10");
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn for_loop_bounds_are_expressions() {
+        let (statements, interner) = parse("for i in a, b * 2 { for j in i..n { x += j; } }");
+
+        let [
+            Statement::For {
+                start, end, body, ..
+            },
+        ] = &statements[..]
+        else {
+            panic!("expected a for loop, got {:?}", statements);
+        };
+
+        assert_eq!(show(start, &interner), "a");
+        assert_eq!(show(end, &interner), "(* b 2)");
+        assert!(matches!(body[..], [Statement::For { .. }]));
+    }
+
+    #[test]
+    fn for_loop_errors() {
+        assert_eq!(
+            errors("for i 0..3 {}"),
+            vec![(expected("'in'", TokenType::Number), 1, 7)]
+        );
+        assert_eq!(
+            errors("for (i) in 0..3 {}"),
+            vec![(expected("identifier", TokenType::LeftParenthesis), 1, 5)]
+        );
+        assert_eq!(
+            errors("for i in 0 {}"),
+            vec![(expected("'..', '..=' or ','", TokenType::LeftBrace), 1, 12)]
+        );
+    }
+
+    #[test]
+    fn bitwise_operators() {
+        assert_eq!(expression("a & b | c"), "(| (& a b) c)");
+        assert_eq!(expression("1 << 3"), "(<< 1 3)");
+        assert_eq!(expression("a <= b << 1"), "(<= a (<< b 1))");
+        assert_eq!(expression("~a & b"), "(& (~ a) b)");
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let (statements, _) = parse("while x { if y { break; } continue; }");
+
+        let [Statement::While { body, .. }] = &statements[..] else {
+            panic!("expected a while, got {:?}", statements);
+        };
+        assert!(matches!(
+            &body[..],
+            [Statement::If { then_branch, .. }, Statement::Continue]
+                if matches!(then_branch[..], [Statement::Break])
+        ));
+
+        assert_eq!(
+            errors("break;"),
+            vec![(ParseErrorKind::OutsideLoop(TokenType::Break), 1, 1)]
+        );
+        assert_eq!(
+            errors("if x { continue; }"),
+            vec![(ParseErrorKind::OutsideLoop(TokenType::Continue), 1, 8)]
+        );
+    }
+
+    #[test]
+    fn boolean_literals() {
+        assert_eq!(expression("true"), "true");
+        assert_eq!(expression("false"), "false");
+        assert_eq!(expression("x == true"), "(== x true)");
+        assert_eq!(parse("while true { break; }").0.len(), 1);
+        assert_eq!(parse("do {} while false;").0.len(), 1);
+    }
+
+    #[test]
+    fn null_literal() {
+        assert_eq!(expression("null"), "null");
+        assert_eq!(expression("x == null"), "(== x null)");
+    }
+
+    #[test]
+    fn match_is_reserved() {
+        assert_eq!(
+            errors("let match = 1;"),
+            vec![(ParseErrorKind::NotImplemented(TokenType::Match), 1, 5)]
+        );
+        assert_eq!(
+            ParseErrorKind::NotImplemented(TokenType::Match).to_string(),
+            "'match' is reserved but not yet implemented"
+        );
+    }
+
+    #[test]
+    fn constants() {
+        let (statements, interner) = parse("const limit = 10; let x = limit * 2;");
+        assert!(matches!(
+            statements[0],
+            Statement::Declaration { constant: true, .. }
+        ));
+
+        let Statement::Declaration { expression, .. } = &statements[1] else {
+            panic!("expected a declaration, got {:?}", statements[1]);
+        };
+        assert_eq!(show(expression, &interner), "(* limit 2)");
+
+        assert_eq!(
+            errors("const limit = 10; limit += 1;"),
+            vec![(
+                ParseErrorKind::ConstantReassignment(String::from("limit")),
+                1,
+                19
+            )]
+        );
+    }
+
+    #[test]
+    fn constants_are_scoped_to_their_block() {
+        assert!(errors("if c { const x = 1; } x = 2;").is_empty());
+        assert!(errors("const x = 1; if c { let x = 2; x = 3; }").is_empty());
+        assert!(errors("const i = 1; for i in 0..3 { i += 1; }").is_empty());
+        assert!(errors("const a = 1; fn f(a) { a = 2; }").is_empty());
+
+        assert_eq!(
+            errors("const x = 1; if c { let x = 2; } x = 3;"),
+            vec![(
+                ParseErrorKind::ConstantReassignment(String::from("x")),
+                1,
+                34
+            )]
+        );
+        assert_eq!(
+            errors("const x = 1; while c { x = 2; }"),
+            vec![(
+                ParseErrorKind::ConstantReassignment(String::from("x")),
+                1,
+                24
+            )]
+        );
+        assert_eq!(
+            errors("let x = 1; const x = 2; x = 3;"),
+            vec![(
+                ParseErrorKind::ConstantReassignment(String::from("x")),
+                1,
+                25
+            )]
+        );
+    }
+
+    #[test]
+    fn word_operators_parse_like_symbols() {
+        assert_eq!(expression("a and not b or c"), expression("a && !b || c"));
+        assert_eq!(expression("a and not b or c"), "(|| (&& a (! b)) c)");
+    }
+
+    #[test]
+    fn string_literals() {
+        assert_eq!(expression(r#""plain""#), r#""plain""#);
+        assert_eq!(
+            expression(r#""a {x + 1} b {y}""#),
+            r#"(interpolation "a " (+ x 1) " b " y)"#
+        );
+        assert_eq!(expression(r#"r"a\nb""#), r#""a\\nb""#);
+    }
+
+    #[test]
+    fn char_literals() {
+        assert_eq!(expression(r"'a' == '\n'"), r"(== 'a' '\n')");
+    }
+
+    #[test]
+    fn pipes_chain_left_to_right_below_everything_else() {
+        assert_eq!(expression("a |> f |> g |> h"), "(|> (|> (|> a f) g) h)");
+        assert_eq!(expression("a + 1 |> f"), "(|> (+ a 1) f)");
+        assert_eq!(expression("a == b |> f"), "(|> (== a b) f)");
+        assert_eq!(expression("a ?? b |> f"), "(|> (?? a b) f)");
+    }
+
+    #[test]
+    fn pipe_statements_may_start_with_a_name() {
+        let (statements, interner) = parse("x |> f |> g; (x) |> f; x;");
+
+        let shown: Vec<String> = statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::Expression(expression) => show(expression, &interner),
+                other => panic!("expected an expression statement, got {:?}", other),
+            })
+            .collect();
+
+        assert_eq!(shown, vec!["(|> (|> x f) g)", "(|> (group x) f)", "x"]);
+    }
+
+    #[test]
+    fn coalescing_groups_to_the_right() {
+        assert_eq!(expression("a ?? b ?? c"), "(?? a (?? b c))");
+        assert_eq!(expression("a == b ?? c"), "(?? (== a b) c)");
+        assert_eq!(expression("a ?? b || c"), "(?? a (|| b c))");
+    }
+
+    #[test]
+    fn member_access() {
+        assert_eq!(expression("a.b?.c"), "(?. (. a b) c)");
+        assert_eq!(expression("-a.b"), "(- (. a b))");
+        assert_eq!(expression("a?.b ** 2"), "(** (?. a b) 2)");
+        assert_eq!(
+            errors("let x = a.;"),
+            vec![(expected("identifier", TokenType::Semicolon), 1, 11)]
+        );
+    }
+
+    #[test]
+    fn member_statements() {
+        let (statements, interner) = parse("a.b; a?.b.c; a.b |> f;");
+
+        let shown: Vec<String> = statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::Expression(expression) => show(expression, &interner),
+                other => panic!("expected an expression statement, got {:?}", other),
+            })
+            .collect();
+
+        assert_eq!(shown, vec!["(. a b)", "(. (?. a b) c)", "(|> (. a b) f)"]);
+
+        // members can be read but not yet assigned to
+        assert_eq!(
+            errors("a.b = 1;"),
+            vec![(expected("';'", TokenType::Equal), 1, 5)]
+        );
+    }
+
+    #[test]
+    fn exponentiation_groups_to_the_right() {
+        assert_eq!(expression("2 ** 3 ** 2"), "(** 2 (** 3 2))");
+        assert_eq!(expression("2 ^ 3 ** 2"), "(^ 2 (** 3 2))");
+        assert_eq!(expression("a ** -b"), "(** a (- b))");
+        assert_eq!(expression("-a ** b"), "(- (** a b))");
+        assert_eq!(expression("a * b ** c"), "(* a (** b c))");
+    }
+
+    #[test]
+    fn newline_termination_is_opt_in() {
+        let source = "let x = 1 +\n  2\nlet y = (x\n  * 3)\nwhile y { y -= 1 }\n";
+
+        let (_, errors, _) = parse_with(source, false);
+        assert!(!errors.is_empty());
+
+        let (statements, errors, interner) = parse_with(source, true);
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        assert_eq!(statements.len(), 3);
+
+        let Statement::Declaration { expression, .. } = &statements[1] else {
+            panic!("expected a declaration, got {:?}", statements[1]);
+        };
+        assert_eq!(show(expression, &interner), "(group (* x 3))");
+    }
+
+    #[test]
+    fn newline_termination_keeps_explicit_semicolons() {
+        let source = "let x = 1; let y = 2;\nx = y\n";
+
+        let (with, errors, _) = parse_with(source, true);
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        assert_eq!(with.len(), 3);
+
+        let (without, _) = parse("let x = 1; let y = 2;\nx = y;\n");
+        assert_eq!(format!("{:?}", with), format!("{:?}", without));
+    }
+
+    #[test]
+    fn newline_termination_lets_a_body_open_on_the_next_line() {
+        let source = "if x\n{\n  y = 1\n}\nfn f(a)\n{\n}\nwhile y\n{ y -= 1 }\n";
+
+        let (statements, errors, _) = parse_with(source, true);
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        assert!(matches!(
+            &statements[..],
+            [
+                Statement::If { .. },
+                Statement::Function { .. },
+                Statement::While { .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn if_statements() {
+        let (statements, interner) = parse("if a { x = 1; }");
+        let [
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch: None,
+            },
+        ] = &statements[..]
+        else {
+            panic!("expected an if, got {:?}", statements);
+        };
+        assert_eq!(show(condition, &interner), "a");
+        assert_eq!(then_branch.len(), 1);
+
+        let (statements, _) = parse("if a { x = 1; } else { x = 2; y = 3; }");
+        assert!(matches!(
+            &statements[..],
+            [Statement::If { else_branch: Some(branch), .. }] if branch.len() == 2
+        ));
+    }
+
+    #[test]
+    fn nested_and_chained_ifs() {
+        let (statements, _) =
+            parse("if a { if b { x = 1; } } else if c { x = 2; } else { x = 3; }");
+
+        let [
+            Statement::If {
+                then_branch,
+                else_branch: Some(else_branch),
+                ..
+            },
+        ] = &statements[..]
+        else {
+            panic!("expected an if, got {:?}", statements);
+        };
+
+        assert!(matches!(then_branch[..], [Statement::If { .. }]));
+        assert!(matches!(
+            else_branch[..],
+            [Statement::If {
+                else_branch: Some(_),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn if_requires_braces() {
+        assert_eq!(
+            errors("if a x = 1;"),
+            vec![(expected("'{'", TokenType::Identifier), 1, 6)]
+        );
+    }
+
+    #[test]
+    fn while_loops() {
+        let (statements, _) = parse("while a {} while b { while c { x = 1; } }");
+
+        assert!(matches!(
+            &statements[..],
+            [
+                Statement::While { body: empty, .. },
+                Statement::While { body: outer, .. },
+            ] if empty.is_empty() && matches!(outer[..], [Statement::While { .. }])
+        ));
+    }
+
+    #[test]
+    fn while_without_a_closing_brace() {
+        assert_eq!(
+            errors("while x { y = 1;"),
+            vec![(
+                ParseErrorKind::UnexpectedEof {
+                    expected: String::from("'}'")
+                },
+                1,
+                17
+            )]
+        );
+    }
+
+    #[test]
+    fn do_while_loops() {
+        let (statements, interner) = parse("do { let y = x; x += y; } while x < 10;");
+
+        let [Statement::DoWhile { body, condition }] = &statements[..] else {
+            panic!("expected a do-while, got {:?}", statements);
+        };
+
+        assert!(matches!(
+            body[..],
+            [Statement::Declaration { .. }, Statement::Assignment { .. }]
+        ));
+        assert_eq!(show(condition, &interner), "(< x 10)");
+    }
+
+    #[test]
+    fn do_while_errors() {
+        assert_eq!(
+            errors("do { x = 1; } x < 3;"),
+            vec![(expected("'while'", TokenType::Identifier), 1, 15)]
+        );
+        assert_eq!(
+            errors("do { x = 1; } while x"),
+            vec![(
+                ParseErrorKind::UnexpectedEof {
+                    expected: String::from("';'")
+                },
+                1,
+                22
+            )]
+        );
+    }
+
+    #[test]
+    fn function_declarations() {
+        let (statements, interner) = parse("fn zero() {} fn pair(a, b,) { a = b; }");
+
+        let [
+            Statement::Function {
+                params: none,
+                body: empty,
+                ..
+            },
+            Statement::Function { name, params, body },
+        ] = &statements[..]
+        else {
+            panic!("expected two functions, got {:?}", statements);
+        };
+
+        assert!(none.is_empty() && empty.is_empty());
+        assert_eq!(interner.resolve(*name), "pair");
+
+        let params: Vec<&str> = params
+            .iter()
+            .map(|param| interner.resolve(*param))
+            .collect();
+        assert_eq!(params, vec!["a", "b"]);
+        assert_eq!(body.len(), 1);
+    }
+
+    #[test]
+    fn functions_may_be_declared_after_their_use() {
+        let (statements, _) = parse("let y = 1 |> double; fn double(x) { x *= 2; }");
+
+        assert!(matches!(
+            statements[..],
+            [Statement::Declaration { .. }, Statement::Function { .. }]
+        ));
+    }
+
+    #[test]
+    fn function_errors() {
+        assert_eq!(
+            errors("fn f(a, a) {}"),
+            vec![(ParseErrorKind::DuplicateParameter(String::from("a")), 1, 9)]
+        );
+        assert_eq!(
+            errors("fn f(a b) {}"),
+            vec![(expected("')'", TokenType::Identifier), 1, 8)]
+        );
+        assert_eq!(
+            errors("fn f(,) {}"),
+            vec![(expected("identifier", TokenType::Comma), 1, 6)]
+        );
+
+        // a function body is not inside the loop around it
+        assert_eq!(
+            errors("while x { fn f() { break; } }"),
+            vec![(ParseErrorKind::OutsideLoop(TokenType::Break), 1, 20)]
+        );
+    }
 }